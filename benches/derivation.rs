@@ -0,0 +1,46 @@
+//! Bulk address derivation, run with `cargo bench --bench derivation`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bitcoin::Network;
+use btc_app::account::account::Account;
+use btc_app::account::address_type::AddressType;
+use btc_app::account::wallet::Wallet;
+
+const MNEMONIC: &str = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+/// Runs `f` for indices `0..count` and prints the time per call.
+fn bench(name: &str, count: u32, mut f: impl FnMut(u32)) -> Duration {
+    let start = Instant::now();
+    for index in 0..count {
+        f(index);
+    }
+    let per_call = start.elapsed() / count;
+    println!("{:<48} {:>10.2?} per address", name, per_call);
+    per_call
+}
+
+fn main() {
+    // stretches the seed and derives the whole path for every address
+    let standalone = bench("Account::from_mnemonic", 100, |index| {
+        black_box(Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, Some([0, index])).unwrap());
+    });
+
+    // derives the last two levels from the cached account key
+    let mut wallet = Wallet::from_mnemonic(Network::Bitcoin, MNEMONIC, "").unwrap();
+    let account = bench("Wallet::account", 2_000, |index| {
+        black_box(wallet.account(AddressType::P2WPKH, 0, [0, index]).unwrap());
+    });
+
+    // derives one public level from the cached chain xpub
+    let count = 20_000;
+    let start = Instant::now();
+    black_box(wallet.addresses(AddressType::P2WPKH, 0, 0, 0..count).unwrap());
+    let addresses = start.elapsed() / count;
+    println!("{:<48} {:>10.2?} per address", "Wallet::addresses", addresses);
+
+    println!();
+    println!("Wallet::account is {:.0}x faster", standalone.as_secs_f64() / account.as_secs_f64());
+    println!("Wallet::addresses is {:.0}x faster", standalone.as_secs_f64() / addresses.as_secs_f64());
+}
@@ -1,58 +1,310 @@
-use std::str::FromStr;
+use std::fmt;
 
-use bip39::{Language, Mnemonic};
 use bitcoin::{
     Address,
-    bip32::{ChildNumber, DerivationPath, Xpriv, Xpub},
-    CompressedPublicKey, key::Secp256k1, Network, PrivateKey, PublicKey,
+    bip32::{ChainCode, ChildNumber, DerivationPath, Xpriv, Xpub},
+    CompressedPublicKey, key::Secp256k1, Network, PrivateKey, PublicKey, Script,
 };
 use bitcoin::key::UntweakedPublicKey;
+use bitcoin::secp256k1::{SecretKey, Verification};
+use bitcoin::NetworkKind;
+use zeroize::Zeroizing;
 
 use crate::errors::{AccountError, Result};
+use crate::secp;
 
 use super::address_type::AddressType;
+use super::bip38;
+use super::electrum;
+use super::mnemonic::{self, Language, WordCount};
+use super::slip39;
+use super::xyz_pub::{convert_version, Version};
 
-#[derive(Debug)]
+/// A single-key account: the key at one HD address, or an imported WIF or raw private key.
+///
+/// Imported keys have no `xpub`, `xpriv` or `hd_path`.
+///
+/// Private keys are only reachable through the `expose_*` accessors, are left out of `Debug`
+/// and `Display`, and are wiped when the account is dropped.
 pub struct Account {
     pub address: Address,
-    pub wif_private_key: String,
+    wif_private_key: Zeroizing<String>,
     pub public_key: PublicKey,
-    pub xpub: Xpub,
-    pub xpriv: Xpriv,
-    pub hd_path: DerivationPath,
+    pub xpub: Option<Xpub>,
+    xpriv: Option<Xpriv>,
+    pub hd_path: Option<DerivationPath>,
+    pub address_type: AddressType,
+    pub network: Network,
 }
 
 impl Account {
     pub fn from_random(address_type: AddressType, network: Network) -> Result<Account> {
-        let mnemonic = Mnemonic::generate_in(Language::English, 12)
-            .map_err(|_| AccountError::InvalidMnemonic)?;
-        Account::from_mnemonic(network, mnemonic.to_string().as_str(), address_type, None)
+        Account::from_random_with_mnemonic(address_type, network).map(|(account, _)| account)
     }
 
+    /// Like [`Account::from_random`], also returning the generated mnemonic so it can be backed up.
+    pub fn from_random_with_mnemonic(
+        address_type: AddressType,
+        network: Network,
+    ) -> Result<(Account, Zeroizing<String>)> {
+        Account::from_random_in(address_type, network, WordCount::Words12, Language::English)
+    }
+
+    /// Like [`Account::from_random_with_mnemonic`] with a mnemonic of `word_count` words from
+    /// the `language` wordlist.
+    pub fn from_random_in(
+        address_type: AddressType,
+        network: Network,
+        word_count: WordCount,
+        language: Language,
+    ) -> Result<(Account, Zeroizing<String>)> {
+        let mnemonic = mnemonic::generate(word_count, language)?;
+        let account = Account::from_mnemonic(network, mnemonic.as_str(), address_type, None)?;
+        Ok((account, mnemonic))
+    }
+
+    /// Imports a WIF private key for `network`, e.g. from a paper wallet. Compression is taken
+    /// from the WIF, which must be encoded for `network`.
+    pub fn from_wif(wif: &str, network: Network, address_type: AddressType) -> Result<Account> {
+        let private_key =
+            PrivateKey::from_wif(wif).map_err(|e| AccountError::InvalidPrivateKey(e.to_string()))?;
+        Account::from_private_key(private_key, network, address_type)
+    }
+
+    /// Imports a raw 32-byte secret key as a compressed key.
+    pub fn from_secret_bytes(secret: &[u8; 32], network: Network, address_type: AddressType) -> Result<Account> {
+        let secret_key =
+            SecretKey::from_slice(secret).map_err(|e| AccountError::InvalidPrivateKey(e.to_string()))?;
+        Account::from_private_key(PrivateKey::new(secret_key, network), network, address_type)
+    }
+
+    /// Imports a BIP-38 encrypted private key.
+    pub fn from_bip38(
+        encrypted: &str,
+        passphrase: &str,
+        network: Network,
+        address_type: AddressType,
+    ) -> Result<Account> {
+        let private_key = bip38::decrypt(encrypted, passphrase, network)?;
+        Account::from_private_key(private_key, network, address_type)
+    }
+
+    /// Imports `private_key` for `network`. Uncompressed keys only have a P2PKH address.
+    pub fn from_private_key(
+        private_key: PrivateKey,
+        network: Network,
+        address_type: AddressType,
+    ) -> Result<Account> {
+        if NetworkKind::from(network) != private_key.network {
+            return Err(AccountError::InvalidPrivateKey(format!(
+                "key is not for {}",
+                network
+            )));
+        }
+        if !private_key.compressed && address_type != AddressType::P2PKH {
+            return Err(AccountError::InvalidPrivateKey(format!(
+                "uncompressed keys cannot be used for {} addresses",
+                address_type
+            )));
+        }
+
+        let secp256k1 = secp::context();
+        let public_key = private_key.public_key(secp256k1);
+        let address = address_for(secp256k1, &public_key, address_type, network);
+
+        Ok(Account {
+            address,
+            wif_private_key: Zeroizing::new(private_key.to_wif()),
+            public_key,
+            xpub: None,
+            xpriv: None,
+            hd_path: None,
+            address_type,
+            network,
+        })
+    }
+
+    /// The private key of `address` in WIF. Callers are responsible for not leaking it.
+    pub fn expose_wif_private_key(&self) -> &str {
+        &self.wif_private_key
+    }
+
+    /// The private key of `address` encrypted with `passphrase` as a BIP-38 `6P...` key.
+    pub fn export_bip38(&self, passphrase: &str) -> Result<String> {
+        bip38::encrypt(&self.private_key()?, passphrase)
+    }
+
+    /// The account level extended private key, `None` for imported keys. Callers are
+    /// responsible for not leaking it.
+    pub fn expose_xpriv(&self) -> Option<&Xpriv> {
+        self.xpriv.as_ref()
+    }
+
+    /// The account xpub with the SLIP-132 version wallets expect for `address_type`, e.g. a
+    /// `zpub` for P2WPKH or a `upub` for testnet P2SH-P2WPKH.
+    pub fn slip132_xpub(&self) -> Result<String> {
+        let xpub = self.xpub.as_ref().ok_or_else(no_extended_key)?;
+        let version = Version::for_address_type(self.address_type, xpub.network, false);
+        convert_version(xpub.to_string(), &version)
+    }
+
+    /// The private counterpart of [`Account::slip132_xpub`], e.g. a `zprv`. Callers are
+    /// responsible for not leaking it.
+    pub fn expose_slip132_xpriv(&self) -> Result<Zeroizing<String>> {
+        let xpriv = self.xpriv.as_ref().ok_or_else(no_extended_key)?;
+        let version = Version::for_address_type(self.address_type, xpriv.network, true);
+        convert_version(Zeroizing::new(xpriv.to_string()).as_str(), &version).map(Zeroizing::new)
+    }
+
+    /// The address at `[chain, index]` below the account xpub, e.g. `[0, 5]` for the sixth
+    /// receiving address of the account.
+    pub fn address_at(&self, path: [u32; 2]) -> Result<Address> {
+        let xpub = self.xpub.as_ref().ok_or_else(no_extended_key)?;
+        let path = [
+            ChildNumber::from_normal_idx(path[0]).map_err(|e| AccountError::InvalidHDPath(e.to_string()))?,
+            ChildNumber::from_normal_idx(path[1]).map_err(|e| AccountError::InvalidHDPath(e.to_string()))?,
+        ];
+        let secp256k1 = secp::context();
+        let child = xpub
+            .derive_pub(secp256k1, &path)
+            .map_err(|e| AccountError::Other(e.to_string()))?;
+        Ok(address_for(secp256k1, &PublicKey::new(child.public_key), self.address_type, self.network))
+    }
+
+    /// The `[chain, index]` below the account xpub whose address pays to `script_pubkey`,
+    /// scanning the first `lookahead` receive and change addresses. Always `None` for
+    /// imported keys.
+    pub fn find_script(&self, script_pubkey: &Script, lookahead: u32) -> Option<[u32; 2]> {
+        let xpub = self.xpub.as_ref()?;
+        let secp256k1 = secp::context();
+        for chain in [0, 1] {
+            let chain_xpub = xpub.derive_pub(secp256k1, &[ChildNumber::from_normal_idx(chain).ok()?]).ok()?;
+            for index in 0..lookahead {
+                let child = chain_xpub.derive_pub(secp256k1, &[ChildNumber::from_normal_idx(index).ok()?]).ok()?;
+                let public_key = PublicKey::new(child.public_key);
+                // the script is the same on every network
+                let address = address_for(secp256k1, &public_key, self.address_type, Network::Bitcoin);
+                if address.script_pubkey() == *script_pubkey {
+                    return Some([chain, index]);
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether `script_pubkey` pays to this account's address or one of the addresses
+    /// [`Account::find_script`] scans.
+    pub fn owns_script(&self, script_pubkey: &Script, lookahead: u32) -> bool {
+        self.address.script_pubkey() == *script_pubkey || self.find_script(script_pubkey, lookahead).is_some()
+    }
+
+    pub(crate) fn private_key(&self) -> Result<PrivateKey> {
+        PrivateKey::from_wif(&self.wif_private_key)
+            .map_err(|e| AccountError::InvalidPrivateKey(e.to_string()))
+    }
+
+    /// The account at `path` below the default path of `account_type`. The mnemonic is stretched
+    /// on every call, derive many accounts of one mnemonic with a [`super::wallet::Wallet`].
     pub fn from_mnemonic(
         network: Network,
         mnemonic: &str,
         account_type: AddressType,
         path: Option<[u32; 2]>,
     ) -> Result<Account> {
-        let mnemonic = Mnemonic::from_str(mnemonic).map_err(|_| AccountError::InvalidMnemonic)?;
+        Account::from_mnemonic_with_passphrase(network, mnemonic, "", account_type, path)
+    }
+
+    /// Like [`Account::from_mnemonic`] with a BIP-39 passphrase, sometimes called the 25th word.
+    pub fn from_mnemonic_with_passphrase(
+        network: Network,
+        mnemonic: &str,
+        passphrase: &str,
+        account_type: AddressType,
+        path: Option<[u32; 2]>,
+    ) -> Result<Account> {
+        let seed = mnemonic::to_seed(mnemonic, passphrase)?;
+        Account::from_seed(network, seed.as_ref(), account_type, path)
+    }
 
-        let seed = mnemonic.to_seed("");
+    /// Recovers the account backed up as SLIP-39 `shares`, whose master secret is the seed.
+    pub fn from_slip39<S: AsRef<str>>(
+        network: Network,
+        shares: &[S],
+        passphrase: &str,
+        account_type: AddressType,
+        path: Option<[u32; 2]>,
+    ) -> Result<Account> {
+        let seed = slip39::combine(shares, passphrase)?;
+        Account::from_seed(network, &seed, account_type, path)
+    }
 
-        let root =
-            Xpriv::new_master(network, &seed).map_err(|e| AccountError::Other(e.to_string()))?;
+    /// Recovers an Electrum standard or segwit wallet from its seed words, with Electrum's
+    /// derivation layout. `path` is `[0, i]` for receiving and `[1, i]` for change addresses.
+    pub fn from_electrum(
+        network: Network,
+        mnemonic: &str,
+        passphrase: &str,
+        path: Option<[u32; 2]>,
+    ) -> Result<Account> {
+        let (seed_type, seed) = electrum::to_seed(mnemonic, passphrase)?;
+        let mut root = Account::root_xpriv(network, seed.as_ref())?;
+        let account =
+            Account::from_root_xpriv(network, &root, seed_type.account_path(), seed_type.address_type(), path);
+        wipe_xpriv(&mut root);
+        account
+    }
+
+    /// The BIP-32 master key of `seed`, the root for [`super::bip85`] child wallets.
+    pub fn root_xpriv(network: Network, seed: &[u8]) -> Result<Xpriv> {
+        Xpriv::new_master(network, seed).map_err(|e| AccountError::Other(e.to_string()))
+    }
+
+    /// The account at the default path of `account_type` under the BIP-32 `seed`.
+    pub fn from_seed(
+        network: Network,
+        seed: &[u8],
+        account_type: AddressType,
+        path: Option<[u32; 2]>,
+    ) -> Result<Account> {
+        let hd_path = account_type.account_path(0)?;
+        let mut root = Account::root_xpriv(network, seed)?;
+        let account = Account::from_root_xpriv(network, &root, hd_path, account_type, path);
+        wipe_xpriv(&mut root);
+        account
+    }
 
+    /// The account at `hd_path` under `root`, for wallets with their own derivation layout.
+    pub fn from_root_xpriv(
+        network: Network,
+        root: &Xpriv,
+        hd_path: DerivationPath,
+        account_type: AddressType,
+        path: Option<[u32; 2]>,
+    ) -> Result<Account> {
         // derive the account xpriv
-        let secp256k1 = Secp256k1::new();
-        // 3 levels pathh
-        let hd_path = DerivationPath::from_str(account_type.default_path())
-            .map_err(|_| AccountError::InvalidHDPath(account_type.default_path().to_string()))?;
-        let xpriv = root
-            .derive_priv(&secp256k1, &hd_path)
+        let secp256k1 = secp::context();
+        let mut xpriv = root
+            .derive_priv(secp256k1, &hd_path)
             .map_err(|e| AccountError::Other(e.to_string()))?;
-        let xpub = Xpub::from_priv(&secp256k1, &xpriv);
+        let xpub = Xpub::from_priv(secp256k1, &xpriv);
+        // the account keeps its own copy
+        let account = Account::from_account_keys(network, &xpriv, xpub, hd_path, account_type, path);
+        wipe_xpriv(&mut xpriv);
+        account
+    }
 
-        // derive the account from xpriv or xpub
+    /// The account whose account level keys, found at `hd_path`, are `xpriv` and `xpub`.
+    pub(crate) fn from_account_keys(
+        network: Network,
+        xpriv: &Xpriv,
+        xpub: Xpub,
+        hd_path: DerivationPath,
+        account_type: AddressType,
+        path: Option<[u32; 2]>,
+    ) -> Result<Account> {
+        let secp256k1 = secp::context();
+
+        // derive the account from xpriv
         let path = path.unwrap_or([0, 0]);
         let first = ChildNumber::from_normal_idx(path[0])
             .map_err(|e| AccountError::Other(e.to_string()))?;
@@ -62,55 +314,108 @@ impl Account {
         // path
         let path = [first, second];
 
-        let public_key = xpub
-            .derive_pub(&secp256k1, &path)
-            .map_err(|e| AccountError::Other(e.to_string()))?
-            .public_key;
-
-        let private_key = xpriv
-            .derive_priv(&secp256k1, &path)
-            .map_err(|e| AccountError::Other(e.to_string()))?
-            .private_key;
+        let mut child = xpriv
+            .derive_priv(secp256k1, &path)
+            .map_err(|e| AccountError::Other(e.to_string()))?;
         // convert the hex private key to wif format
-        let wif_private_key = PrivateKey::new(private_key, network).to_wif();
-
-        let public_key = PublicKey::new(public_key);
-        let address = match account_type {
-            AddressType::P2PKH => Address::p2pkh(&public_key, network),
-            AddressType::P2SH_P2WPKH => {
-                let compressed = CompressedPublicKey(public_key.inner);
-                Address::p2shwpkh(&compressed, network)
-            }
-            AddressType::P2WPKH => {
-                let compressed = CompressedPublicKey(public_key.inner);
-                Address::p2wpkh(&compressed, network)
-            }
-            AddressType::P2TR => {
-                let untweaked_public_key = public_key.inner;
-                Address::p2tr(
-                    &secp256k1,
-                    UntweakedPublicKey::from(untweaked_public_key),
-                    None,
-                    network,
-                )
-            }
-            _ => Address::p2pkh(&public_key, network),
-        };
+        let wif_private_key = Zeroizing::new(PrivateKey::new(child.private_key, network).to_wif());
+
+        // one multiplication, cheaper than deriving the public path from xpub
+        let public_key = PublicKey::new(child.private_key.public_key(secp256k1));
+        wipe_xpriv(&mut child);
+        let address = address_for(secp256k1, &public_key, account_type, network);
 
         Ok(Account {
             address,
-            xpub: xpub,
-            xpriv: xpriv,
-            wif_private_key: wif_private_key,
-            public_key: public_key,
-            hd_path,
+            xpub: Some(xpub),
+            xpriv: Some(*xpriv),
+            wif_private_key,
+            public_key,
+            hd_path: Some(hd_path),
+            address_type: account_type,
+            network,
         })
     }
 }
 
+/// Overwrites the private key and chain code of `xpriv`. Best effort, bitcoin's key types cannot
+/// be zeroized directly and copies of them may remain.
+pub(crate) fn wipe_xpriv(xpriv: &mut Xpriv) {
+    xpriv.private_key.non_secure_erase();
+    xpriv.chain_code = ChainCode::from([0u8; 32]);
+}
+
+fn no_extended_key() -> AccountError {
+    AccountError::InvalidExtendedKey("imported single keys have no extended key".to_string())
+}
+
+/// The `address_type` address of `public_key`, which must be compressed unless it is P2PKH.
+pub(crate) fn address_for<C: Verification>(
+    secp256k1: &Secp256k1<C>,
+    public_key: &PublicKey,
+    address_type: AddressType,
+    network: Network,
+) -> Address {
+    match address_type {
+        AddressType::P2PKH => Address::p2pkh(public_key, network),
+        AddressType::P2SH_P2WPKH => {
+            let compressed = CompressedPublicKey(public_key.inner);
+            Address::p2shwpkh(&compressed, network)
+        }
+        AddressType::P2WPKH => {
+            let compressed = CompressedPublicKey(public_key.inner);
+            Address::p2wpkh(&compressed, network)
+        }
+        AddressType::P2TR => {
+            let untweaked_public_key = public_key.inner;
+            Address::p2tr(
+                secp256k1,
+                UntweakedPublicKey::from(untweaked_public_key),
+                None,
+                network,
+            )
+        }
+    }
+}
+
+impl Drop for Account {
+    fn drop(&mut self) {
+        if let Some(xpriv) = self.xpriv.as_mut() {
+            wipe_xpriv(xpriv);
+        }
+    }
+}
+
+impl fmt::Debug for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Account")
+            .field("address", &self.address)
+            .field("wif_private_key", &"<redacted>")
+            .field("public_key", &self.public_key)
+            .field("xpub", &self.xpub)
+            .field("xpriv", &self.xpriv.as_ref().map(|_| "<redacted>"))
+            .field("hd_path", &self.hd_path)
+            .field("address_type", &self.address_type)
+            .field("network", &self.network)
+            .finish()
+    }
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.hd_path {
+            Some(hd_path) => write!(f, "{} ({} {})", self.address, self.address_type, hd_path),
+            None => write!(f, "{} ({} imported)", self.address, self.address_type),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use bip39::Mnemonic;
+
     use super::*;
+    use crate::account::bip85;
 
     #[test]
     fn test_derive_account_from_mnemonic() {
@@ -124,12 +429,12 @@ mod tests {
         // assert eq
         assert_eq!(
             "xprv9y6ctoiHQPqekbRQi8EYzw1DxD47E6S5iERL4kREhuTWXfTzkd12CcfVeRen942QQ7cnszCYgattcBdNSR2r4WCzj4hcfCo5cyjr8AFoqEa".to_string(),
-            account.xpriv.to_string()
+            account.expose_xpriv().unwrap().to_string()
         );
 
         assert_eq!(
             "xpub6C5yJKFBEmPwy5Vsp9mZN4wxWEtbdZ9w5TLvs8prGEzVQTo9JAKGkQyyViBS7frzQeMWK4mb1sUYNgQSfkn1vpfU21cJiTjyy32wZnrXWUP".to_string(),
-            account.xpub.to_string()
+            account.xpub.unwrap().to_string()
         );
 
         assert_eq!(
@@ -142,7 +447,7 @@ mod tests {
         );
         assert_eq!(
             "Kz4AiXLbrKWPnghcLcVTdDmN3hSBEKBX2vqTVrvG7MchpMYqzL7N".to_string(),
-            account.wif_private_key
+            account.expose_wif_private_key()
         );
     }
 
@@ -165,7 +470,7 @@ mod tests {
         );
         assert_eq!(
             "KzNg2w1LmYkDSCUkVBo9W7F3ioLVVF8WkDshxjQGh7iSDrAau798".to_string(),
-            account.wif_private_key
+            account.expose_wif_private_key()
         );
     }
 
@@ -188,13 +493,277 @@ mod tests {
         );
         assert_eq!(
             "L3vQW2hH334dzaSEFxukFxSP2665XwR5R2qqg5aTzwpPWK8ppdd4".to_string(),
-            account.wif_private_key
+            account.expose_wif_private_key()
         );
     }
 
     #[test]
     fn test_from_random() {
         let account = Account::from_random(AddressType::P2PKH, Network::Bitcoin).unwrap();
-        println!("Account: {:?}", account);
+        assert!(account.expose_wif_private_key().starts_with(['K', 'L']));
+    }
+
+    #[test]
+    fn test_export_bip38() {
+        let test_mnemoic =
+            "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let account =
+            Account::from_mnemonic(Network::Bitcoin, test_mnemoic, AddressType::P2WPKH, None)
+                .unwrap();
+
+        let encrypted = account.export_bip38("TestingOneTwoThree").unwrap();
+        assert!(encrypted.starts_with("6PY"));
+
+        let decrypted = bip38::decrypt(&encrypted, "TestingOneTwoThree", Network::Bitcoin).unwrap();
+        assert_eq!(decrypted.to_wif(), account.expose_wif_private_key());
+    }
+
+    #[test]
+    fn test_debug_and_display_redact_secrets() {
+        let test_mnemoic =
+            "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let account =
+            Account::from_mnemonic(Network::Bitcoin, test_mnemoic, AddressType::P2WPKH, None)
+                .unwrap();
+
+        for shown in [format!("{:?}", account), format!("{:#?}", account), account.to_string()] {
+            assert!(shown.to_lowercase().contains("bc1qvgsr2jt7wzxcalv82045rzd9ed8pxljdwck269"));
+            assert!(!shown.contains(account.expose_wif_private_key()));
+            assert!(!shown.contains(&account.expose_xpriv().unwrap().to_string()));
+            assert!(!shown.contains(&hex::encode(account.expose_xpriv().unwrap().private_key.secret_bytes())));
+        }
+    }
+
+    #[test]
+    fn test_from_wif_matches_from_mnemonic() {
+        let test_mnemoic =
+            "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        for address_type in [
+            AddressType::P2PKH,
+            AddressType::P2SH_P2WPKH,
+            AddressType::P2WPKH,
+            AddressType::P2TR,
+        ] {
+            let hd = Account::from_mnemonic(Network::Bitcoin, test_mnemoic, address_type, None).unwrap();
+            let imported = Account::from_wif(hd.expose_wif_private_key(), Network::Bitcoin, address_type).unwrap();
+
+            assert_eq!(imported.address, hd.address);
+            assert_eq!(imported.public_key, hd.public_key);
+            assert_eq!(imported.expose_wif_private_key(), hd.expose_wif_private_key());
+            assert!(imported.xpub.is_none() && imported.expose_xpriv().is_none() && imported.hd_path.is_none());
+
+            let private_key = PrivateKey::from_wif(hd.expose_wif_private_key()).unwrap();
+            let raw = Account::from_secret_bytes(&private_key.inner.secret_bytes(), Network::Bitcoin, address_type)
+                .unwrap();
+            assert_eq!(raw.address, hd.address);
+        }
+    }
+
+    #[test]
+    fn test_from_wif_uncompressed_and_testnet() {
+        // BIP-38 test vector key, uncompressed
+        let wif = "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR";
+        let account = Account::from_wif(wif, Network::Bitcoin, AddressType::P2PKH).unwrap();
+        assert_eq!(account.address.to_string(), "1Jq6MksXQVWzrznvZzxkV6oY57oWXD9TXB");
+        assert!(!account.public_key.compressed);
+        assert_eq!(account.to_string(), "1Jq6MksXQVWzrznvZzxkV6oY57oWXD9TXB (p2pkh imported)");
+        assert!(matches!(
+            Account::from_wif(wif, Network::Bitcoin, AddressType::P2WPKH),
+            Err(AccountError::InvalidPrivateKey(_))
+        ));
+
+        let private_key = PrivateKey::from_wif(wif).unwrap();
+        let testnet_wif = PrivateKey::new(private_key.inner, Network::Testnet).to_wif();
+        let account = Account::from_wif(&testnet_wif, Network::Testnet, AddressType::P2WPKH).unwrap();
+        assert!(account.address.to_string().starts_with("tb1q"));
+        let account = Account::from_wif(&testnet_wif, Network::Regtest, AddressType::P2WPKH).unwrap();
+        assert!(account.address.to_string().starts_with("bcrt1q"));
+        assert!(matches!(
+            Account::from_wif(&testnet_wif, Network::Bitcoin, AddressType::P2WPKH),
+            Err(AccountError::InvalidPrivateKey(_))
+        ));
+        assert!(Account::from_wif(wif, Network::Testnet, AddressType::P2PKH).is_err());
+
+        assert!(Account::from_wif("not a wif", Network::Bitcoin, AddressType::P2PKH).is_err());
+        assert!(Account::from_secret_bytes(&[0u8; 32], Network::Bitcoin, AddressType::P2PKH).is_err());
+    }
+
+    #[test]
+    fn test_from_bip38() {
+        let account = Account::from_bip38(
+            "6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGg",
+            "TestingOneTwoThree",
+            Network::Bitcoin,
+            AddressType::P2PKH,
+        )
+        .unwrap();
+        assert_eq!(
+            account.expose_wif_private_key(),
+            "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR"
+        );
+    }
+
+    #[test]
+    fn test_from_random_in() {
+        for language in [Language::English, Language::Japanese, Language::Spanish] {
+            let (account, mnemonic) =
+                Account::from_random_in(AddressType::P2WPKH, Network::Bitcoin, WordCount::Words24, language)
+                    .unwrap();
+            assert_eq!(Mnemonic::parse_in(language, mnemonic.as_str()).unwrap().word_count(), 24);
+
+            let restored =
+                Account::from_mnemonic(Network::Bitcoin, &mnemonic, AddressType::P2WPKH, None).unwrap();
+            assert_eq!(restored.address, account.address);
+        }
+    }
+
+    #[test]
+    fn test_from_slip39() {
+        let test_mnemoic =
+            "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let account =
+            Account::from_mnemonic(Network::Bitcoin, test_mnemoic, AddressType::P2WPKH, None).unwrap();
+
+        let seed = mnemonic::to_seed(test_mnemoic, "").unwrap();
+        let groups = [slip39::GroupSpec::new(2, 3)];
+        let shares = slip39::split(seed.as_ref(), "", 1, &groups, 0, false).unwrap();
+        let recovered = Account::from_slip39(
+            Network::Bitcoin,
+            &[shares[0][2].as_str(), shares[0][0].as_str()],
+            "",
+            AddressType::P2WPKH,
+            None,
+        )
+        .unwrap();
+        assert_eq!(recovered.address, account.address);
+    }
+
+    #[test]
+    fn test_bip85_child_account() {
+        let test_mnemoic =
+            "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let seed = mnemonic::to_seed(test_mnemoic, "").unwrap();
+        let root = Account::root_xpriv(Network::Bitcoin, seed.as_ref()).unwrap();
+
+        let child = bip85::mnemonic(&root, WordCount::Words12, Language::English, 0).unwrap();
+        let first = Account::from_mnemonic(Network::Bitcoin, &child, AddressType::P2WPKH, None).unwrap();
+        let again = bip85::mnemonic(&root, WordCount::Words12, Language::English, 0).unwrap();
+        assert_eq!(again, child);
+
+        let other = bip85::mnemonic(&root, WordCount::Words12, Language::English, 1).unwrap();
+        let second = Account::from_mnemonic(Network::Bitcoin, &other, AddressType::P2WPKH, None).unwrap();
+        assert_ne!(first.address, second.address);
+    }
+
+    #[test]
+    fn test_wipe_xpriv() {
+        let seed = mnemonic::to_seed("wish film peasant much sure thought speed print napkin hard crumble envelope", "")
+            .unwrap();
+        let root = Account::root_xpriv(Network::Bitcoin, seed.as_ref()).unwrap();
+        let mut wiped = root;
+        wipe_xpriv(&mut wiped);
+
+        assert_ne!(wiped.private_key, root.private_key);
+        assert_eq!(wiped.chain_code, ChainCode::from([0u8; 32]));
+        // the account keeps its own key when the root is wiped
+        let account = Account::from_seed(Network::Bitcoin, seed.as_ref(), AddressType::P2WPKH, None).unwrap();
+        let hd_path = AddressType::P2WPKH.account_path(0).unwrap();
+        let again = Account::from_root_xpriv(Network::Bitcoin, &root, hd_path, AddressType::P2WPKH, None).unwrap();
+        assert_eq!(account.address, again.address);
+        assert_ne!(account.expose_xpriv().unwrap().chain_code, wiped.chain_code);
+    }
+
+    #[test]
+    fn test_from_electrum() {
+        // from Electrum's wallet tests
+        let standard = Account::from_electrum(
+            Network::Bitcoin,
+            "cycle rocket west magnet parrot shuffle foot correct salt library feed song",
+            "",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            standard.xpub.unwrap().to_string(),
+            "xpub661MyMwAqRbcFWohJWt7PHsFEJfZAvw9ZxwQoDa4SoMgsDDM1T7WK3u9E4edkC4ugRnZ8E4xDZRpk8Rnts3Nbt97dPwT52CwBdDWroaZf8U"
+        );
+        assert_eq!(standard.address.to_string(), "1NNkttn1YvVGdqBW4PR6zvc3Zx3H5owKRf");
+
+        let segwit = Account::from_electrum(
+            Network::Bitcoin,
+            "bitter grass shiver impose acquire brush forget axis eager alone wine silver",
+            "",
+            None,
+        )
+        .unwrap();
+        assert_eq!(segwit.address.to_string(), "bc1q3g5tmkmlvxryhh843v4dz026avatc0zzr6h3af");
+        assert_eq!(segwit.hd_path.as_ref().unwrap().to_string(), "0'");
+
+        assert!(Account::from_electrum(
+            Network::Bitcoin,
+            "wish film peasant much sure thought speed print napkin hard crumble envelope",
+            "",
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_slip132_export() {
+        let test_mnemoic =
+            "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let expected = [
+            (AddressType::P2PKH, Network::Bitcoin, "xpub", "xprv"),
+            (AddressType::P2SH_P2WPKH, Network::Bitcoin, "ypub", "yprv"),
+            (AddressType::P2WPKH, Network::Bitcoin, "zpub", "zprv"),
+            (AddressType::P2TR, Network::Bitcoin, "xpub", "xprv"),
+            (AddressType::P2WPKH, Network::Testnet, "vpub", "vprv"),
+        ];
+        for (address_type, network, public, private) in expected {
+            let account = Account::from_mnemonic(network, test_mnemoic, address_type, None).unwrap();
+
+            let xpub = account.slip132_xpub().unwrap();
+            assert!(xpub.starts_with(public));
+            let neutral = Version::for_address_type(AddressType::P2PKH, network.into(), false);
+            assert_eq!(convert_version(&xpub, &neutral).unwrap(), account.xpub.unwrap().to_string());
+
+            let xpriv = account.expose_slip132_xpriv().unwrap();
+            assert!(xpriv.starts_with(private));
+            let neutral = Version::for_address_type(AddressType::P2PKH, network.into(), true);
+            assert_eq!(
+                convert_version(xpriv.as_str(), &neutral).unwrap(),
+                account.expose_xpriv().unwrap().to_string()
+            );
+        }
+
+        let imported =
+            Account::from_wif("Kz4AiXLbrKWPnghcLcVTdDmN3hSBEKBX2vqTVrvG7MchpMYqzL7N", Network::Bitcoin, AddressType::P2WPKH)
+                .unwrap();
+        assert!(imported.slip132_xpub().is_err());
+        assert!(imported.expose_slip132_xpriv().is_err());
+    }
+
+    #[test]
+    fn test_find_script() {
+        let mnemonic = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let account = Account::from_mnemonic(Network::Bitcoin, mnemonic, AddressType::P2TR, None).unwrap();
+        let change = Account::from_mnemonic(Network::Bitcoin, mnemonic, AddressType::P2TR, Some([1, 7])).unwrap();
+        let script = change.address.script_pubkey();
+
+        assert_eq!(account.address_at([1, 7]).unwrap(), change.address);
+        assert_eq!(account.find_script(&script, 10), Some([1, 7]));
+        assert_eq!(account.find_script(&script, 5), None);
+        assert!(account.owns_script(&script, 10));
+        assert!(account.owns_script(&account.address.script_pubkey(), 0));
+
+        // same key, other address type
+        let segwit =
+            Account::from_mnemonic(Network::Bitcoin, mnemonic, AddressType::P2WPKH, Some([1, 7])).unwrap();
+        assert!(!account.owns_script(&segwit.address.script_pubkey(), 10));
+
+        let imported = Account::from_wif(change.expose_wif_private_key(), Network::Bitcoin, AddressType::P2TR).unwrap();
+        assert_eq!(imported.find_script(&script, 10), None);
+        assert!(imported.address_at([0, 0]).is_err());
+        assert!(imported.owns_script(&script, 0));
     }
 }
@@ -1,35 +1,38 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use serde::{Deserialize, Serialize};
+
 use crate::errors::{AccountError, Result};
+
+/// Serialized and displayed as its lowercase name, e.g. `p2sh_p2wpkh`. Parsing also accepts the
+/// wallet style names `legacy`, `segwit`, `native_segwit` and `taproot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AddressType {
     /// legacy m/44'/0'/0'
+    #[serde(rename = "p2pkh", alias = "legacy")]
     P2PKH,
     /// native segwit m/84'/0'/0'
+    #[serde(rename = "p2wpkh", alias = "native_segwit")]
     P2WPKH,
     /// taproot m/86'/0'/0'
+    #[serde(rename = "p2tr", alias = "taproot")]
     P2TR,
     /// nested segwit m/49'/0'/0'
+    #[allow(non_camel_case_types)]
+    #[serde(rename = "p2sh_p2wpkh", alias = "segwit")]
     P2SH_P2WPKH,
 }
 
 impl AddressType {
-    pub fn from_str(s: &str) -> Result<AddressType> {
-        match s {
-            "p2pkh" => Ok(AddressType::P2PKH),
-            "p2wpkh" => Ok(AddressType::P2WPKH),
-            "p2tr" => Ok(AddressType::P2TR),
-            "p2sh_p2wpkh" => Ok(AddressType::P2SH_P2WPKH),
-            _ => Err(AccountError::InvalidAddressType(s.to_string())),
-        }
-    }
-
-    pub fn to_string(&self) -> String {
-        match self {
-            AddressType::P2PKH => "p2pkh".to_string(),
-            AddressType::P2WPKH => "p2wpkh".to_string(),
-            AddressType::P2TR => "p2tr".to_string(),
-            AddressType::P2SH_P2WPKH => "p2sh_p2wpkh".to_string(),
-
-        }
-    }
+    /// Every address type, in BIP-44, 49, 84, 86 order.
+    pub const ALL: [AddressType; 4] = [
+        AddressType::P2PKH,
+        AddressType::P2SH_P2WPKH,
+        AddressType::P2WPKH,
+        AddressType::P2TR,
+    ];
 
     pub fn default_path(&self) -> &str {
         match self {
@@ -39,9 +42,76 @@ impl AddressType {
             AddressType::P2TR=> "m/86'/0'/0'",
         }
     }
+
+    /// The path of account `index`, [`AddressType::default_path`] being account 0.
+    pub fn account_path(&self, index: u32) -> Result<DerivationPath> {
+        let default_path = DerivationPath::from_str(self.default_path())
+            .map_err(|_| AccountError::InvalidHDPath(self.default_path().to_string()))?;
+        let account = ChildNumber::from_hardened_idx(index)
+            .map_err(|e| AccountError::InvalidHDPath(e.to_string()))?;
+        Ok(DerivationPath::from(default_path[..2].to_vec()).child(account))
+    }
 }
 
+impl FromStr for AddressType {
+    type Err = AccountError;
 
+    fn from_str(s: &str) -> Result<AddressType> {
+        match s {
+            "p2pkh" | "legacy" => Ok(AddressType::P2PKH),
+            "p2wpkh" | "native_segwit" => Ok(AddressType::P2WPKH),
+            "p2tr" | "taproot" => Ok(AddressType::P2TR),
+            "p2sh_p2wpkh" | "segwit" => Ok(AddressType::P2SH_P2WPKH),
+            _ => Err(AccountError::InvalidAddressType(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AddressType::P2PKH => "p2pkh",
+            AddressType::P2WPKH => "p2wpkh",
+            AddressType::P2TR => "p2tr",
+            AddressType::P2SH_P2WPKH => "p2sh_p2wpkh",
+        };
+        f.write_str(name)
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn test_from_str_and_display() {
+        for address_type in AddressType::ALL {
+            assert_eq!(address_type.to_string().parse::<AddressType>().unwrap(), address_type);
+        }
+        assert_eq!(AddressType::P2SH_P2WPKH.to_string(), "p2sh_p2wpkh");
+        assert_eq!("legacy".parse::<AddressType>().unwrap(), AddressType::P2PKH);
+        assert_eq!("segwit".parse::<AddressType>().unwrap(), AddressType::P2SH_P2WPKH);
+        assert_eq!("native_segwit".parse::<AddressType>().unwrap(), AddressType::P2WPKH);
+        assert_eq!("taproot".parse::<AddressType>().unwrap(), AddressType::P2TR);
+        assert!(matches!("p2wsh".parse::<AddressType>(), Err(AccountError::InvalidAddressType(_))));
+    }
+
+    #[test]
+    fn test_account_path() {
+        assert_eq!(AddressType::P2TR.account_path(0).unwrap().to_string(), "86'/0'/0'");
+        assert_eq!(AddressType::P2SH_P2WPKH.account_path(3).unwrap().to_string(), "49'/0'/3'");
+        assert!(AddressType::P2PKH.account_path(1 << 31).is_err());
+    }
 
+    #[test]
+    fn test_serde() {
+        let json = serde_json::to_string(&AddressType::ALL).unwrap();
+        assert_eq!(json, r#"["p2pkh","p2sh_p2wpkh","p2wpkh","p2tr"]"#);
+        assert_eq!(serde_json::from_str::<Vec<AddressType>>(&json).unwrap(), AddressType::ALL);
+
+        let aliases: Vec<AddressType> =
+            serde_json::from_str(r#"["legacy","segwit","native_segwit","taproot"]"#).unwrap();
+        assert_eq!(aliases, AddressType::ALL);
+        assert!(serde_json::from_str::<AddressType>(r#""P2TR""#).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::Aes256;
+use bitcoin::base58;
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::secp256k1::{Scalar, SecretKey};
+use bitcoin::{Address, Network, PrivateKey};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+use crate::errors::{AccountError, Result};
+use crate::secp;
+
+/// Prefix of keys encrypted without EC multiplication, `6P...`
+const PREFIX_NON_EC: [u8; 2] = [0x01, 0x42];
+
+/// Prefix of keys encrypted with EC multiplication from an intermediate code, also `6P...`
+const PREFIX_EC: [u8; 2] = [0x01, 0x43];
+
+const FLAG_NON_EC: u8 = 0xc0;
+const FLAG_COMPRESSED: u8 = 0x20;
+const FLAG_LOT_SEQUENCE: u8 = 0x04;
+
+/// Encrypts `private_key` with `passphrase` as a non-EC-multiply BIP-38 key.
+pub fn encrypt(private_key: &PrivateKey, passphrase: &str) -> Result<String> {
+    let address_hash = address_hash(private_key);
+
+    let derived = scrypt(normalize(passphrase).as_bytes(), &address_hash, 14, 8, 8)?;
+    let (half1, half2) = derived.split_at(32);
+
+    let secret = Zeroizing::new(private_key.inner.secret_bytes());
+    let mut block1 = xor16(&secret[..16], &half1[..16]);
+    let mut block2 = xor16(&secret[16..], &half1[16..]);
+    let cipher = Aes256::new(GenericArray::from_slice(half2));
+    cipher.encrypt_block(GenericArray::from_mut_slice(block1.as_mut()));
+    cipher.encrypt_block(GenericArray::from_mut_slice(block2.as_mut()));
+
+    let mut flag = FLAG_NON_EC;
+    if private_key.compressed {
+        flag |= FLAG_COMPRESSED;
+    }
+
+    let mut payload = Vec::with_capacity(39);
+    payload.extend_from_slice(&PREFIX_NON_EC);
+    payload.push(flag);
+    payload.extend_from_slice(&address_hash);
+    payload.extend_from_slice(block1.as_ref());
+    payload.extend_from_slice(block2.as_ref());
+
+    Ok(base58::encode_check(&payload))
+}
+
+/// Decrypts a BIP-38 key, with or without EC multiplication, into a private key for `network`.
+///
+/// Fails with [`AccountError::Bip38WrongPassphrase`] if the decrypted key does not match the
+/// address hash stored in the encrypted key.
+pub fn decrypt(encrypted: &str, passphrase: &str, network: Network) -> Result<PrivateKey> {
+    let payload = base58::decode_check(encrypted)
+        .map_err(|e| AccountError::InvalidBip38(e.to_string()))?;
+    if payload.len() != 39 {
+        return Err(AccountError::InvalidBip38(format!(
+            "expected 39 bytes, got {}",
+            payload.len()
+        )));
+    }
+
+    let flag = payload[2];
+    let compressed = flag & FLAG_COMPRESSED != 0;
+    let address_hash: [u8; 4] = payload[3..7].try_into().expect("4 bytes");
+    let passphrase = normalize(passphrase);
+
+    let secret = match [payload[0], payload[1]] {
+        PREFIX_NON_EC => {
+            if flag & !(FLAG_NON_EC | FLAG_COMPRESSED) != 0 || flag & FLAG_NON_EC != FLAG_NON_EC {
+                return Err(AccountError::InvalidBip38(format!("invalid flag byte {flag:#04x}")));
+            }
+            decrypt_non_ec(&payload, passphrase.as_bytes(), &address_hash)?
+        }
+        PREFIX_EC => {
+            if flag & !(FLAG_COMPRESSED | FLAG_LOT_SEQUENCE) != 0 {
+                return Err(AccountError::InvalidBip38(format!("invalid flag byte {flag:#04x}")));
+            }
+            decrypt_ec(&payload, passphrase.as_bytes(), &address_hash, flag & FLAG_LOT_SEQUENCE != 0)?
+        }
+        _ => return Err(AccountError::InvalidBip38("unknown prefix".to_string())),
+    };
+
+    let private_key = PrivateKey {
+        compressed,
+        network: network.into(),
+        inner: secret,
+    };
+
+    if address_hash != self::address_hash(&private_key) {
+        return Err(AccountError::Bip38WrongPassphrase);
+    }
+
+    Ok(private_key)
+}
+
+fn decrypt_non_ec(payload: &[u8], passphrase: &[u8], address_hash: &[u8; 4]) -> Result<SecretKey> {
+    let derived = scrypt(passphrase, address_hash, 14, 8, 8)?;
+    let (half1, half2) = derived.split_at(32);
+
+    let cipher = Aes256::new(GenericArray::from_slice(half2));
+    let mut block1 = Zeroizing::new(<[u8; 16]>::try_from(&payload[7..23]).expect("16 bytes"));
+    let mut block2 = Zeroizing::new(<[u8; 16]>::try_from(&payload[23..39]).expect("16 bytes"));
+    cipher.decrypt_block(GenericArray::from_mut_slice(block1.as_mut()));
+    cipher.decrypt_block(GenericArray::from_mut_slice(block2.as_mut()));
+
+    let mut secret = Zeroizing::new([0u8; 32]);
+    secret[..16].copy_from_slice(xor16(block1.as_ref(), &half1[..16]).as_ref());
+    secret[16..].copy_from_slice(xor16(block2.as_ref(), &half1[16..]).as_ref());
+
+    // a wrong passphrase can decrypt to zero or a value above the curve order
+    SecretKey::from_slice(secret.as_ref()).map_err(|_| AccountError::Bip38WrongPassphrase)
+}
+
+fn decrypt_ec(payload: &[u8], passphrase: &[u8], address_hash: &[u8; 4], lot_sequence: bool) -> Result<SecretKey> {
+    let owner_entropy = &payload[7..15];
+    let owner_salt = if lot_sequence { &owner_entropy[..4] } else { owner_entropy };
+
+    let prefactor = scrypt(passphrase, owner_salt, 14, 8, 8)?;
+    let pass_factor = if lot_sequence {
+        let mut preimage = Zeroizing::new(prefactor[..32].to_vec());
+        preimage.extend_from_slice(owner_entropy);
+        Zeroizing::new(sha256d::Hash::hash(&preimage).to_byte_array().to_vec())
+    } else {
+        Zeroizing::new(prefactor[..32].to_vec())
+    };
+    let pass_factor = SecretKey::from_slice(&pass_factor).map_err(|_| AccountError::Bip38WrongPassphrase)?;
+    let pass_point = pass_factor.public_key(secp::context()).serialize();
+
+    let mut salt = address_hash.to_vec();
+    salt.extend_from_slice(owner_entropy);
+    let derived = scrypt(&pass_point, &salt, 10, 1, 1)?;
+    let (half1, half2) = derived.split_at(32);
+    let cipher = Aes256::new(GenericArray::from_slice(half2));
+
+    // encrypted part 2 holds the second half of encrypted part 1 and the end of seedb
+    let mut part2 = Zeroizing::new(<[u8; 16]>::try_from(&payload[23..39]).expect("16 bytes"));
+    cipher.decrypt_block(GenericArray::from_mut_slice(part2.as_mut()));
+    let part2 = xor16(part2.as_ref(), &half1[16..]);
+
+    let mut part1 = Zeroizing::new([0u8; 16]);
+    part1[..8].copy_from_slice(&payload[15..23]);
+    part1[8..].copy_from_slice(&part2[..8]);
+    cipher.decrypt_block(GenericArray::from_mut_slice(part1.as_mut()));
+    let part1 = xor16(part1.as_ref(), &half1[..16]);
+
+    let mut seed_b = Zeroizing::new([0u8; 24]);
+    seed_b[..16].copy_from_slice(part1.as_ref());
+    seed_b[16..].copy_from_slice(&part2[8..]);
+
+    let factor_b = sha256d::Hash::hash(seed_b.as_ref()).to_byte_array();
+    let factor_b = Scalar::from_be_bytes(factor_b).map_err(|_| AccountError::Bip38WrongPassphrase)?;
+
+    pass_factor
+        .mul_tweak(&factor_b)
+        .map_err(|_| AccountError::Bip38WrongPassphrase)
+}
+
+/// First four bytes of the double SHA-256 of the key's P2PKH address.
+fn address_hash(private_key: &PrivateKey) -> [u8; 4] {
+    let public_key = private_key.public_key(secp::context());
+    let address = Address::p2pkh(public_key, private_key.network);
+    let hash = sha256d::Hash::hash(address.to_string().as_bytes());
+    hash.to_byte_array()[..4].try_into().expect("4 bytes")
+}
+
+fn normalize(passphrase: &str) -> Zeroizing<String> {
+    Zeroizing::new(passphrase.nfc().collect())
+}
+
+fn scrypt(password: &[u8], salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<Zeroizing<[u8; 64]>> {
+    let params = scrypt::Params::new(log_n, r, p, 64).map_err(|e| AccountError::Other(e.to_string()))?;
+    let mut output = Zeroizing::new([0u8; 64]);
+    scrypt::scrypt(password, salt, &params, output.as_mut()).map_err(|e| AccountError::Other(e.to_string()))?;
+    Ok(output)
+}
+
+fn xor16(a: &[u8], b: &[u8]) -> Zeroizing<[u8; 16]> {
+    let mut out = Zeroizing::new([0u8; 16]);
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = a[i] ^ b[i];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0038.mediawiki#test-vectors
+    const NON_EC: [(&str, &str, &str); 5] = [
+        (
+            "TestingOneTwoThree",
+            "6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGg",
+            "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR",
+        ),
+        (
+            "Satoshi",
+            "6PRNFFkZc2NZ6dJqFfhRoFNMR9Lnyj7dYGrzdgXXVMXcxoKTePPX1dWByq",
+            "5HtasZ6ofTHP6HCwTqTkLDuLQisYPah7aUnSKfC7h4hMUVw2gi5",
+        ),
+        (
+            "\u{03D2}\u{0301}\u{0000}\u{010400}\u{01F4A9}",
+            "6PRW5o9FLp4gJDDVqJQKJFTpMvdsSGJxMYHtHaQBF3ooa8mwD69bapcDQn",
+            "5Jajm8eQ22H3pGWLEVCXyvND8dQZhiQhoLJNKjYXk9roUFTMSZ4",
+        ),
+        (
+            "TestingOneTwoThree",
+            "6PYNKZ1EAgYgmQfmNVamxyXVWHzK5s6DGhwP4J5o44cvXdoY7sRzhtpUeo",
+            "L44B5gGEpqEDRS9vVPz7QT35jcBG2r3CZwSwQ4fCewXAhAhqGVpP",
+        ),
+        (
+            "Satoshi",
+            "6PYLtMnXvfG3oJde97zRyLYFZCYizPU5T3LwgdYJz1fRhh16bU7u6PPmY7",
+            "KwYgW8gcxj1JWJXhPSu4Fqwzfhp5Yfi42mdYmMa4XqK7NJxXUSK7",
+        ),
+    ];
+
+    const EC_MULTIPLY: [(&str, &str, &str); 4] = [
+        (
+            "TestingOneTwoThree",
+            "6PfQu77ygVyJLZjfvMLyhLMQbYnu5uguoJJ4kMCLqWwPEdfpwANVS76gTX",
+            "5K4caxezwjGCGfnoPTZ8tMcJBLB7Jvyjv4xxeacadhq8nLisLR2",
+        ),
+        (
+            "Satoshi",
+            "6PfLGnQs6VZnrNpmVKfjotbnQuaJK4KZoPFrAjx1JMJUa1Ft8gnf5WxfKd",
+            "5KJ51SgxWaAYR13zd9ReMhJpwrcX47xTJh2D3fGPG9CM8vkv5sH",
+        ),
+        (
+            "MOLON LABE",
+            "6PgNBNNzDkKdhkT6uJntUXwwzQV8Rr2tZcbkDcuC9DZRsS6AtHts4Ypo1j",
+            "5JLdxTtcTHcfYcmJsNVy1v2PMDx432JPoYcBTVVRHpPaxUrdtf8",
+        ),
+        (
+            "ΜΟΛΩΝ ΛΑΒΕ",
+            "6PgGWtx25kUg8QWvwuJAgorN6k9FbE25rv5dMRwu5SKMnfpfVe5mar2ngH",
+            "5KMKKuUmAkiNbA3DazMQiLfDq47qs8MAEThm4yL8R2PhV1ov33D",
+        ),
+    ];
+
+    #[test]
+    fn test_non_ec_multiply_vectors() {
+        for (passphrase, encrypted, wif) in NON_EC {
+            let private_key = PrivateKey::from_wif(wif).unwrap();
+            assert_eq!(encrypt(&private_key, passphrase).unwrap(), encrypted);
+            assert_eq!(decrypt(encrypted, passphrase, Network::Bitcoin).unwrap(), private_key);
+        }
+    }
+
+    #[test]
+    fn test_ec_multiply_vectors() {
+        for (passphrase, encrypted, wif) in EC_MULTIPLY {
+            let private_key = PrivateKey::from_wif(wif).unwrap();
+            assert_eq!(decrypt(encrypted, passphrase, Network::Bitcoin).unwrap(), private_key);
+        }
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let (_, encrypted, _) = NON_EC[0];
+        assert!(matches!(
+            decrypt(encrypted, "wrong", Network::Bitcoin),
+            Err(AccountError::Bip38WrongPassphrase)
+        ));
+
+        let (_, encrypted, _) = EC_MULTIPLY[0];
+        assert!(matches!(
+            decrypt(encrypted, "wrong", Network::Bitcoin),
+            Err(AccountError::Bip38WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        // a valid WIF is base58check but not a BIP-38 payload
+        assert!(matches!(
+            decrypt("5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR", "", Network::Bitcoin),
+            Err(AccountError::InvalidBip38(_))
+        ));
+        assert!(matches!(
+            decrypt("6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGh", "", Network::Bitcoin),
+            Err(AccountError::InvalidBip38(_))
+        ));
+    }
+}
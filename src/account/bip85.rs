@@ -0,0 +1,206 @@
+//! BIP-85 deterministic entropy: child mnemonics, WIFs and xprvs derived from one root key, so
+//! a single backup covers any number of independent wallets.
+
+use bitcoin::bip32::{ChainCode, ChildNumber, DerivationPath, Fingerprint, Xpriv};
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::{Network, NetworkKind, PrivateKey};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+use crate::errors::{AccountError, Result};
+use crate::secp;
+
+use super::account::wipe_xpriv;
+use super::mnemonic::{self, Language, WordCount};
+
+/// Purpose of every BIP-85 path, "BIPS" on a phone keypad.
+const PURPOSE: u32 = 83696968;
+const APP_BIP39: u32 = 39;
+const APP_WIF: u32 = 2;
+const APP_XPRV: u32 = 32;
+
+/// The 64 bytes of entropy at the all hardened `path` below `root`.
+pub fn derive_entropy(root: &Xpriv, path: &DerivationPath) -> Result<Zeroizing<[u8; 64]>> {
+    if path.is_empty() || path.into_iter().any(|child| child.is_normal()) {
+        return Err(AccountError::InvalidHDPath(format!("{}, BIP-85 paths are hardened", path)));
+    }
+
+    let mut key = root
+        .derive_priv(secp::context(), path)
+        .map_err(|e| AccountError::Other(e.to_string()))?;
+    let mut mac = Hmac::<Sha512>::new_from_slice(b"bip-entropy-from-k").expect("hmac takes any key length");
+    mac.update(Zeroizing::new(key.private_key.secret_bytes()).as_ref());
+    wipe_xpriv(&mut key);
+
+    let mut entropy = Zeroizing::new([0u8; 64]);
+    entropy.copy_from_slice(&mac.finalize().into_bytes());
+    Ok(entropy)
+}
+
+/// The child mnemonic number `index` of `word_count` words in `language`.
+pub fn mnemonic(
+    root: &Xpriv,
+    word_count: WordCount,
+    language: Language,
+    index: u32,
+) -> Result<Zeroizing<String>> {
+    let path = path(&[APP_BIP39, language_code(language)?, word_count.words() as u32, index])?;
+    let entropy = derive_entropy(root, &path)?;
+    mnemonic::from_entropy(&entropy[..word_count.entropy_bytes()], language)
+}
+
+/// The child private key number `index`, as a compressed WIF for `network`, which must be the
+/// network of `root`.
+pub fn wif(root: &Xpriv, network: Network, index: u32) -> Result<Zeroizing<String>> {
+    if NetworkKind::from(network) != root.network {
+        return Err(AccountError::InvalidExtendedKey(format!("root key is not for {}", network)));
+    }
+    let entropy = derive_entropy(root, &path(&[APP_WIF, index])?)?;
+    let secret_key =
+        SecretKey::from_slice(&entropy[..32]).map_err(|e| AccountError::Other(e.to_string()))?;
+    Ok(Zeroizing::new(PrivateKey::new(secret_key, network).to_wif()))
+}
+
+/// The child root extended private key number `index`.
+pub fn xprv(root: &Xpriv, index: u32) -> Result<Xpriv> {
+    let entropy = derive_entropy(root, &path(&[APP_XPRV, index])?)?;
+    let chain_code: [u8; 32] = entropy[..32].try_into().expect("32 bytes");
+    let private_key =
+        SecretKey::from_slice(&entropy[32..]).map_err(|e| AccountError::Other(e.to_string()))?;
+
+    Ok(Xpriv {
+        network: root.network,
+        depth: 0,
+        parent_fingerprint: Fingerprint::default(),
+        child_number: ChildNumber::from_normal_idx(0).expect("0 is a normal index"),
+        private_key,
+        chain_code: ChainCode::from(chain_code),
+    })
+}
+
+/// `m/83696968'/` followed by `indexes`, all hardened.
+fn path(indexes: &[u32]) -> Result<DerivationPath> {
+    std::iter::once(PURPOSE)
+        .chain(indexes.iter().copied())
+        .map(|index| {
+            ChildNumber::from_hardened_idx(index).map_err(|e| AccountError::InvalidHDPath(e.to_string()))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(DerivationPath::from)
+}
+
+/// Language codes of BIP-85. Wordlists added to BIP-39 after it have none.
+const LANGUAGE_CODES: [(Language, u32); 9] = [
+    (Language::English, 0),
+    (Language::Japanese, 1),
+    (Language::Korean, 2),
+    (Language::Spanish, 3),
+    (Language::SimplifiedChinese, 4),
+    (Language::TraditionalChinese, 5),
+    (Language::French, 6),
+    (Language::Italian, 7),
+    (Language::Czech, 8),
+];
+
+fn language_code(language: Language) -> Result<u32> {
+    LANGUAGE_CODES
+        .iter()
+        .find(|(known, _)| *known == language)
+        .map(|(_, code)| *code)
+        .ok_or_else(|| AccountError::UnsupportedLanguage(format!("{:?} has no BIP-85 code", language)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::account::account::Account;
+    use crate::account::address_type::AddressType;
+
+    // root key of the BIP-85 test vectors
+    const ROOT: &str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+
+    fn root() -> Xpriv {
+        Xpriv::from_str(ROOT).unwrap()
+    }
+
+    #[test]
+    fn test_entropy_vectors() {
+        let path = DerivationPath::from_str("m/83696968'/0'/0'").unwrap();
+        assert_eq!(
+            hex::encode(derive_entropy(&root(), &path).unwrap().as_slice()),
+            "efecfbccffea313214232d29e71563d941229afb4338c21f9517c41aaa0d16f00b83d2a09ef747e7a64e8e2bd5a14869e693da66ce94ac2da570ab7ee48618f7"
+        );
+
+        let path = DerivationPath::from_str("m/83696968'/0'/1'").unwrap();
+        assert_eq!(
+            hex::encode(derive_entropy(&root(), &path).unwrap().as_slice()),
+            "70c6e3e8ebee8dc4c0dbba66076819bb8c09672527c4277ca8729532ad711872218f826919f6b67218adde99018a6df9095ab2b58d803b5b93ec9802085a690e"
+        );
+
+        let unhardened = DerivationPath::from_str("m/83696968'/0'/0").unwrap();
+        assert!(derive_entropy(&root(), &unhardened).is_err());
+    }
+
+    #[test]
+    fn test_mnemonic_vectors() {
+        let vectors = [
+            (WordCount::Words12, "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"),
+            (
+                WordCount::Words18,
+                "near account window bike charge season chef number sketch tomorrow excuse sniff circle vital hockey outdoor supply token",
+            ),
+            (
+                WordCount::Words24,
+                "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano",
+            ),
+        ];
+        for (word_count, expected) in vectors {
+            assert_eq!(mnemonic(&root(), word_count, Language::English, 0).unwrap().as_str(), expected);
+        }
+
+        let japanese = mnemonic(&root(), WordCount::Words12, Language::Japanese, 0).unwrap();
+        assert_eq!(mnemonic::parse(&japanese).unwrap().language(), Language::Japanese);
+        assert_ne!(
+            mnemonic(&root(), WordCount::Words12, Language::English, 1).unwrap().as_str(),
+            "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"
+        );
+    }
+
+    #[test]
+    fn test_language_codes() {
+        assert_eq!(language_code(Language::English).unwrap(), 0);
+        assert_eq!(language_code(Language::Czech).unwrap(), 8);
+        assert!(matches!(
+            mnemonic(&root(), WordCount::Words12, Language::Portuguese, 0),
+            Err(AccountError::UnsupportedLanguage(_))
+        ));
+        for language in Language::ALL {
+            match language_code(*language) {
+                Ok(_) => assert!(mnemonic(&root(), WordCount::Words12, *language, 0).is_ok()),
+                Err(e) => assert!(matches!(e, AccountError::UnsupportedLanguage(_))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_wif_and_xprv_vectors() {
+        assert_eq!(
+            wif(&root(), Network::Bitcoin, 0).unwrap().as_str(),
+            "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp"
+        );
+        assert!(matches!(wif(&root(), Network::Regtest, 0), Err(AccountError::InvalidExtendedKey(_))));
+
+        // the WIF encoding is shared by every test network, the account keeps the network given
+        let regtest_root = Xpriv::new_master(Network::Regtest, &[7u8; 32]).unwrap();
+        let regtest_wif = wif(&regtest_root, Network::Regtest, 0).unwrap();
+        let account = Account::from_wif(&regtest_wif, Network::Regtest, AddressType::P2WPKH).unwrap();
+        assert!(account.address.to_string().starts_with("bcrt1q"));
+        assert_eq!(
+            xprv(&root(), 0).unwrap().to_string(),
+            "xprv9s21ZrQH143K2srSbCSg4m4kLvPMzcWydgmKEnMmoZUurYuBuYG46c6P71UGXMzmriLzCCBvKQWBUv3vPB3m1SATMhp3uEjXHJ42jFg7myX"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Finds which accounts of an imported wallet were used, whatever BIP-44, 49, 84 or 86 layout
+//! the wallet it came from followed.
+
+use bitcoin::bip32::Xpriv;
+use bitcoin::{Address, Network};
+
+use crate::errors::Result;
+
+use super::account::{wipe_xpriv, Account};
+use super::address_type::AddressType;
+use super::mnemonic;
+
+/// Number of consecutive unused addresses after which a chain is considered exhausted.
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// Tells whether an address was ever used, typically backed by an indexer or electrum server.
+pub trait ActivitySource {
+    fn has_activity(&mut self, address: &Address) -> Result<bool>;
+}
+
+impl<F: FnMut(&Address) -> Result<bool>> ActivitySource for F {
+    fn has_activity(&mut self, address: &Address) -> Result<bool> {
+        self(address)
+    }
+}
+
+/// An account with at least one used address.
+#[derive(Debug)]
+pub struct ActiveAccount {
+    /// the account at its first receiving address
+    pub account: Account,
+    /// account index in the derivation path, `n` in `m/84'/0'/n'`
+    pub index: u32,
+    /// highest used receiving and change address index
+    pub last_used: [Option<u32>; 2],
+}
+
+/// Scans every [`AddressType`] of `root` the BIP-44 way: accounts are checked in order until
+/// one has no activity, and each chain until `gap_limit` consecutive addresses are unused.
+pub fn discover<S: ActivitySource + ?Sized>(
+    network: Network,
+    root: &Xpriv,
+    source: &mut S,
+    gap_limit: u32,
+) -> Result<Vec<ActiveAccount>> {
+    let mut active = Vec::new();
+    for address_type in AddressType::ALL {
+        for index in 0.. {
+            let hd_path = address_type.account_path(index)?;
+            let account = Account::from_root_xpriv(network, root, hd_path, address_type, None)?;
+            let last_used = [
+                scan_chain(&account, 0, source, gap_limit)?,
+                scan_chain(&account, 1, source, gap_limit)?,
+            ];
+            if last_used == [None, None] {
+                break;
+            }
+            active.push(ActiveAccount { account, index, last_used });
+        }
+    }
+    Ok(active)
+}
+
+/// Like [`discover`] for the wallet of a BIP-39 `mnemonic` and `passphrase`.
+pub fn discover_mnemonic<S: ActivitySource + ?Sized>(
+    network: Network,
+    mnemonic: &str,
+    passphrase: &str,
+    source: &mut S,
+    gap_limit: u32,
+) -> Result<Vec<ActiveAccount>> {
+    let seed = mnemonic::to_seed(mnemonic, passphrase)?;
+    let mut root = Account::root_xpriv(network, seed.as_ref())?;
+    let active = discover(network, &root, source, gap_limit);
+    wipe_xpriv(&mut root);
+    active
+}
+
+/// The highest used index of `chain`, at least one address is always checked.
+fn scan_chain<S: ActivitySource + ?Sized>(
+    account: &Account,
+    chain: u32,
+    source: &mut S,
+    gap_limit: u32,
+) -> Result<Option<u32>> {
+    let mut last_used = None;
+    let mut unused = 0;
+    let mut index = 0;
+    while unused < gap_limit.max(1) {
+        if source.has_activity(&account.address_at([chain, index])?)? {
+            last_used = Some(index);
+            unused = 0;
+        } else {
+            unused += 1;
+        }
+        index += 1;
+    }
+    Ok(last_used)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::errors::AccountError;
+
+    const MNEMONIC: &str = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    fn address(address_type: AddressType, account: u32, path: [u32; 2]) -> Address {
+        let seed = mnemonic::to_seed(MNEMONIC, "").unwrap();
+        let root = Account::root_xpriv(Network::Bitcoin, seed.as_ref()).unwrap();
+        let hd_path = address_type.account_path(account).unwrap();
+        Account::from_root_xpriv(Network::Bitcoin, &root, hd_path, address_type, Some(path))
+            .unwrap()
+            .address
+            .clone()
+    }
+
+    #[test]
+    fn test_discover() {
+        let used: HashSet<Address> = [
+            address(AddressType::P2WPKH, 0, [0, 0]),
+            address(AddressType::P2WPKH, 0, [0, 4]),
+            address(AddressType::P2WPKH, 0, [1, 2]),
+            address(AddressType::P2WPKH, 1, [0, 3]),
+            // beyond the gap of 5 after index 4
+            address(AddressType::P2WPKH, 0, [0, 10]),
+            address(AddressType::P2TR, 0, [1, 0]),
+            // account 1 of P2PKH is never reached since account 0 is unused
+            address(AddressType::P2PKH, 1, [0, 0]),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut source = |address: &Address| Ok(used.contains(address));
+        let active = discover_mnemonic(Network::Bitcoin, MNEMONIC, "", &mut source, 5).unwrap();
+
+        let found: Vec<_> = active
+            .iter()
+            .map(|active| (active.account.address_type, active.index, active.last_used))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (AddressType::P2WPKH, 0, [Some(4), Some(2)]),
+                (AddressType::P2WPKH, 1, [Some(3), None]),
+                (AddressType::P2TR, 0, [None, Some(0)]),
+            ]
+        );
+        assert_eq!(active[0].account.address, address(AddressType::P2WPKH, 0, [0, 0]));
+    }
+
+    #[test]
+    fn test_discover_propagates_source_errors() {
+        let mut source = |_: &Address| Err(AccountError::Other("offline".to_string()));
+        assert!(discover_mnemonic(Network::Bitcoin, MNEMONIC, "", &mut source, 5).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Electrum "v2" seeds. They are not BIP-39: the seed type is encoded in a hash of the words
+//! instead of a checksum, and the wallet uses Electrum's own derivation layout.
+
+use std::str::FromStr;
+
+use bitcoin::bip32::DerivationPath;
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha512;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+use crate::errors::{AccountError, Result};
+
+use super::address_type::AddressType;
+
+const PBKDF2_ROUNDS: u32 = 2048;
+
+/// Type of an Electrum seed, read from its version hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SeedType {
+    /// P2PKH wallet, addresses at `m/0/i`
+    Standard,
+    /// P2WPKH wallet, addresses at `m/0'/0/i`
+    Segwit,
+}
+
+impl SeedType {
+    fn version_prefix(self) -> &'static str {
+        match self {
+            SeedType::Standard => "01",
+            SeedType::Segwit => "100",
+        }
+    }
+
+    pub fn address_type(self) -> AddressType {
+        match self {
+            SeedType::Standard => AddressType::P2PKH,
+            SeedType::Segwit => AddressType::P2WPKH,
+        }
+    }
+
+    /// Account level path, receiving addresses are at `0/i` and change at `1/i` below it.
+    pub fn account_path(self) -> DerivationPath {
+        match self {
+            SeedType::Standard => DerivationPath::master(),
+            SeedType::Segwit => DerivationPath::from_str("m/0'").expect("valid path"),
+        }
+    }
+}
+
+/// The type of the Electrum seed `mnemonic`, `None` if it is not a standard or segwit seed.
+/// Two-factor seeds are not supported.
+pub fn seed_type(mnemonic: &str) -> Option<SeedType> {
+    let normalized = normalize(mnemonic);
+    let mut mac = Hmac::<Sha512>::new_from_slice(b"Seed version").expect("hmac takes any key length");
+    mac.update(normalized.as_bytes());
+    let version = hex::encode(mac.finalize().into_bytes());
+
+    [SeedType::Standard, SeedType::Segwit]
+        .into_iter()
+        .find(|seed_type| version.starts_with(seed_type.version_prefix()))
+}
+
+/// The BIP-32 seed of the Electrum seed `mnemonic` with the optional seed extension `passphrase`.
+pub fn to_seed(mnemonic: &str, passphrase: &str) -> Result<(SeedType, Zeroizing<[u8; 64]>)> {
+    let seed_type = seed_type(mnemonic).ok_or(AccountError::InvalidMnemonic)?;
+
+    let mnemonic = normalize(mnemonic);
+    let salt = Zeroizing::new(format!("electrum{}", normalize(passphrase).as_str()));
+    let mut seed = Zeroizing::new([0u8; 64]);
+    pbkdf2_hmac::<Sha512>(mnemonic.as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS, seed.as_mut());
+    Ok((seed_type, seed))
+}
+
+/// Electrum's text normalization: NFKD, lowercase, accents removed, whitespace collapsed and
+/// dropped between CJK characters.
+fn normalize(text: &str) -> Zeroizing<String> {
+    let text = Zeroizing::new(
+        text.nfkd()
+            .flat_map(char::to_lowercase)
+            .filter(|c| !is_combining_mark(*c))
+            .collect::<String>(),
+    );
+    let chars = text.split_whitespace().collect::<Vec<_>>().join(" ").chars().collect::<Vec<_>>();
+
+    let mut normalized = Zeroizing::new(String::with_capacity(chars.len()));
+    for (i, c) in chars.iter().enumerate() {
+        let between_cjk = *c == ' ' && is_cjk(chars[i - 1]) && is_cjk(chars[i + 1]);
+        if !between_cjk {
+            normalized.push(*c);
+        }
+    }
+    normalized
+}
+
+/// The CJK scripts Electrum writes seeds in.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11ff // Hangul Jamo
+            | 0x2e80..=0x2fdf // CJK radicals
+            | 0x3000..=0x30ff // CJK punctuation, Hiragana, Katakana
+            | 0x3130..=0x318f // Hangul compatibility Jamo
+            | 0x3400..=0x4dbf // CJK extension A
+            | 0x4e00..=0x9fff // CJK unified ideographs
+            | 0xac00..=0xd7af // Hangul syllables
+            | 0xf900..=0xfaff // CJK compatibility ideographs
+            | 0x20000..=0x2fa1f // CJK extensions B to F and supplement
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_type() {
+        assert_eq!(
+            seed_type("cycle rocket west magnet parrot shuffle foot correct salt library feed song"),
+            Some(SeedType::Standard)
+        );
+        assert_eq!(
+            seed_type("bitter grass shiver impose acquire brush forget axis eager alone wine silver"),
+            Some(SeedType::Segwit)
+        );
+        // case and whitespace do not matter
+        assert_eq!(
+            seed_type(" Bitter  grass shiver impose acquire brush forget axis eager alone wine SILVER"),
+            Some(SeedType::Segwit)
+        );
+        assert_eq!(
+            seed_type("wish film peasant much sure thought speed print napkin hard crumble envelope"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_to_seed_vectors() {
+        // from Electrum's test_mnemonic.py
+        let words = "wild father tree among universe such mobile favorite target dynamic credit identify";
+        let (seed_type, seed) = to_seed(words, "").unwrap();
+        assert_eq!(seed_type, SeedType::Segwit);
+        assert_eq!(
+            hex::encode(seed.as_slice()),
+            "aac2a6302e48577ab4b46f23dbae0774e2e62c796f797d0a1b5faeb528301e3064342dafb79069e7c4c6b8c38ae11d7a973bec0d4f70626f8cc5184a8d0b0756"
+        );
+
+        let (_, seed) = to_seed(words, "Did you ever hear the tragedy of Darth Plagueis the Wise?").unwrap();
+        assert_eq!(
+            hex::encode(seed.as_slice()),
+            "4aa29f2aeb0127efb55138ab9e7be83b36750358751906f86c662b21a1ea1370f949e6d1a12fa56d3d93cadda93038c76ac8118597364e46f5156fde6183c82f"
+        );
+
+        assert!(to_seed("wish film peasant much sure thought speed print napkin hard crumble envelope", "").is_err());
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Élan   vital ").as_str(), "elan vital");
+        assert_eq!(normalize("あいう えお abc def").as_str(), "あいうえお abc def");
+    }
+}
@@ -0,0 +1,420 @@
+use std::fs;
+use std::path::Path;
+
+use bitcoin::Network;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::errors::{AccountError, Result};
+
+use super::account::Account;
+use super::address_type::AddressType;
+
+/// Current keystore format version.
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// Highest scrypt `log_n` accepted, 2^20 rounds already need 1 GiB of memory at `r = 8`.
+/// Keystores are untrusted input and must not make loading them exhaust the machine, so the
+/// memory scrypt needs, `128 * r * 2^log_n` bytes, and its parallelism `p` are capped too.
+pub const MAX_SCRYPT_LOG_N: u8 = 20;
+pub const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+pub const MAX_SCRYPT_P: u32 = 4;
+
+const KDF_SCRYPT: &str = "scrypt";
+const CIPHER_XCHACHA20_POLY1305: &str = "xchacha20poly1305";
+
+/// scrypt cost parameters, stored in the keystore so they can be raised later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    /// 2^17 rounds, about a quarter of a second on a laptop.
+    fn default() -> Self {
+        ScryptParams {
+            log_n: 17,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub kdf: String,
+    pub kdf_params: ScryptParams,
+    /// hex encoded
+    pub salt: String,
+    pub cipher: String,
+    /// hex encoded
+    pub nonce: String,
+    /// hex encoded mnemonic ciphertext followed by the poly1305 tag
+    pub ciphertext: String,
+}
+
+/// Password encrypted mnemonic, stored as JSON.
+///
+/// The mnemonic is encrypted with XChaCha20-Poly1305 under a scrypt derived key. Every other field
+/// is authenticated as associated data, so editing the network, address type or passphrase flag
+/// makes decryption fail just like a wrong password does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub network: Network,
+    pub address_type: AddressType,
+    /// Whether the mnemonic was used with a BIP-39 passphrase. The passphrase itself is never stored.
+    pub has_passphrase: bool,
+    pub crypto: KeystoreCrypto,
+}
+
+impl Keystore {
+    pub fn encrypt(
+        mnemonic: &str,
+        has_passphrase: bool,
+        network: Network,
+        address_type: AddressType,
+        password: &str,
+    ) -> Result<Keystore> {
+        Keystore::encrypt_with_params(
+            mnemonic,
+            has_passphrase,
+            network,
+            address_type,
+            password,
+            ScryptParams::default(),
+        )
+    }
+
+    pub fn encrypt_with_params(
+        mnemonic: &str,
+        has_passphrase: bool,
+        network: Network,
+        address_type: AddressType,
+        password: &str,
+        kdf_params: ScryptParams,
+    ) -> Result<Keystore> {
+        // refuse to store something we could not load again
+        Account::from_mnemonic(network, mnemonic, address_type, None)?;
+
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let mut keystore = Keystore {
+            version: KEYSTORE_VERSION,
+            network,
+            address_type,
+            has_passphrase,
+            crypto: KeystoreCrypto {
+                kdf: KDF_SCRYPT.to_string(),
+                kdf_params,
+                salt: hex::encode(salt),
+                cipher: CIPHER_XCHACHA20_POLY1305.to_string(),
+                nonce: hex::encode(nonce),
+                ciphertext: String::new(),
+            },
+        };
+
+        let key = derive_key(password, &salt, &kdf_params)?;
+        let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(key.as_ref()));
+        let aad = keystore.associated_data();
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: mnemonic.as_bytes(),
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| AccountError::Other("keystore encryption failed".to_string()))?;
+        keystore.crypto.ciphertext = hex::encode(ciphertext);
+
+        Ok(keystore)
+    }
+
+    /// Decrypts the stored mnemonic.
+    /// Fails with [`AccountError::KeystoreDecryption`] on a wrong password or any tampering.
+    pub fn decrypt_mnemonic(&self, password: &str) -> Result<Zeroizing<String>> {
+        self.check_format()?;
+
+        let salt = decode_hex("salt", &self.crypto.salt)?;
+        let nonce = decode_hex("nonce", &self.crypto.nonce)?;
+        let ciphertext = decode_hex("ciphertext", &self.crypto.ciphertext)?;
+        if nonce.len() != 24 {
+            return Err(AccountError::InvalidKeystore("nonce must be 24 bytes".to_string()));
+        }
+
+        let key = derive_key(password, &salt, &self.crypto.kdf_params)?;
+        let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(key.as_ref()));
+        let aad = self.associated_data();
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(
+                    XNonce::from_slice(&nonce),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| AccountError::KeystoreDecryption)?,
+        );
+
+        let mnemonic = std::str::from_utf8(&plaintext).map_err(|_| AccountError::KeystoreDecryption)?;
+        Ok(Zeroizing::new(mnemonic.to_string()))
+    }
+
+    /// Decrypts the keystore and derives its account at `path`, see [`Account::from_mnemonic`].
+    /// `passphrase` must be given if and only if the keystore was saved with one.
+    pub fn to_account(&self, password: &str, passphrase: Option<&str>, path: Option<[u32; 2]>) -> Result<Account> {
+        let passphrase = match (self.has_passphrase, passphrase) {
+            (true, Some(passphrase)) => passphrase,
+            (true, None) => return Err(AccountError::PassphraseRequired),
+            (false, Some(_)) => return Err(AccountError::PassphraseNotExpected),
+            (false, None) => "",
+        };
+
+        let mnemonic = self.decrypt_mnemonic(password)?;
+        Account::from_mnemonic_with_passphrase(self.network, &mnemonic, passphrase, self.address_type, path)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| AccountError::InvalidKeystore(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Keystore> {
+        let keystore: Keystore =
+            serde_json::from_str(json).map_err(|e| AccountError::InvalidKeystore(e.to_string()))?;
+        keystore.check_format()?;
+        Ok(keystore)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_json()?).map_err(|e| AccountError::Other(e.to_string()))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Keystore> {
+        let json = fs::read_to_string(path).map_err(|e| AccountError::Other(e.to_string()))?;
+        Keystore::from_json(&json)
+    }
+
+    fn check_format(&self) -> Result<()> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(AccountError::UnsupportedKeystoreVersion(self.version));
+        }
+        if self.crypto.kdf != KDF_SCRYPT {
+            return Err(AccountError::InvalidKeystore(format!("unsupported kdf {}", self.crypto.kdf)));
+        }
+        if self.crypto.cipher != CIPHER_XCHACHA20_POLY1305 {
+            return Err(AccountError::InvalidKeystore(format!(
+                "unsupported cipher {}",
+                self.crypto.cipher
+            )));
+        }
+        Ok(())
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        let params = &self.crypto.kdf_params;
+        format!(
+            "btc_app-keystore:{}:{}:{}:{}:{}:{}:{}:{}",
+            self.version,
+            self.network,
+            self.address_type,
+            self.has_passphrase,
+            self.crypto.kdf,
+            params.log_n,
+            params.r,
+            params.p,
+        )
+        .into_bytes()
+    }
+}
+
+fn derive_key(password: &str, salt: &[u8], params: &ScryptParams) -> Result<Zeroizing<[u8; 32]>> {
+    if params.log_n > MAX_SCRYPT_LOG_N {
+        return Err(AccountError::KeystoreCostTooHigh(format!(
+            "log_n {} is above {}",
+            params.log_n, MAX_SCRYPT_LOG_N
+        )));
+    }
+    let memory = (128 * u64::from(params.r)) << params.log_n;
+    if memory > MAX_SCRYPT_MEMORY {
+        return Err(AccountError::KeystoreCostTooHigh(format!(
+            "{} bytes of memory is above {}",
+            memory, MAX_SCRYPT_MEMORY
+        )));
+    }
+    if params.p > MAX_SCRYPT_P {
+        return Err(AccountError::KeystoreCostTooHigh(format!("p {} is above {}", params.p, MAX_SCRYPT_P)));
+    }
+    let scrypt_params = scrypt::Params::new(params.log_n, params.r, params.p, 32)
+        .map_err(|e| AccountError::InvalidKeystore(e.to_string()))?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(password.as_bytes(), salt, &scrypt_params, key.as_mut())
+        .map_err(|e| AccountError::InvalidKeystore(e.to_string()))?;
+    Ok(key)
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|_| AccountError::InvalidKeystore(format!("{field} is not valid hex")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MNEMONIC: &str =
+        "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    // keep the tests fast, real keystores use the default cost
+    const FAST: ScryptParams = ScryptParams { log_n: 4, r: 8, p: 1 };
+
+    fn keystore(has_passphrase: bool) -> Keystore {
+        Keystore::encrypt_with_params(
+            TEST_MNEMONIC,
+            has_passphrase,
+            Network::Bitcoin,
+            AddressType::P2WPKH,
+            "correct horse battery staple",
+            FAST,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_through_file() {
+        let (account, mnemonic) =
+            Account::from_random_with_mnemonic(AddressType::P2TR, Network::Testnet).unwrap();
+        let keystore =
+            Keystore::encrypt_with_params(&mnemonic, false, Network::Testnet, AddressType::P2TR, "pw", FAST)
+                .unwrap();
+
+        let path = std::env::temp_dir().join(format!("btc_app-keystore-{}.json", account.address));
+        keystore.save(&path).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        let loaded = Keystore::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(!json.contains(mnemonic.as_str()));
+        assert_eq!(loaded, keystore);
+        assert_eq!(loaded.to_account("pw", None, None).unwrap().address, account.address);
+    }
+
+    #[test]
+    fn test_passphrase_flag() {
+        let keystore = keystore(true);
+
+        assert!(matches!(
+            keystore.to_account("correct horse battery staple", None, None),
+            Err(AccountError::PassphraseRequired)
+        ));
+
+        let account = keystore
+            .to_account("correct horse battery staple", Some("TREZOR"), None)
+            .unwrap();
+        let expected = Account::from_mnemonic_with_passphrase(
+            Network::Bitcoin,
+            TEST_MNEMONIC,
+            "TREZOR",
+            AddressType::P2WPKH,
+            None,
+        )
+        .unwrap();
+        assert_eq!(account.address, expected.address);
+        assert_ne!(
+            account.address.to_string(),
+            "bc1qvgsr2jt7wzxcalv82045rzd9ed8pxljdwck269"
+        );
+    }
+
+    #[test]
+    fn test_passphrase_not_expected() {
+        assert!(matches!(
+            keystore(false).to_account("correct horse battery staple", Some("TREZOR"), None),
+            Err(AccountError::PassphraseNotExpected)
+        ));
+    }
+
+    #[test]
+    fn test_scrypt_cost_is_capped() {
+        let mut expensive = keystore(false);
+        expensive.crypto.kdf_params.log_n = 40;
+        assert!(matches!(
+            expensive.decrypt_mnemonic("correct horse battery staple"),
+            Err(AccountError::KeystoreCostTooHigh(_))
+        ));
+
+        // allowed log_n, but 128 GiB of memory
+        let mut wide = keystore(false);
+        wide.crypto.kdf_params = ScryptParams { log_n: MAX_SCRYPT_LOG_N, r: 1 << 20, p: 1 };
+        assert!(matches!(
+            wide.decrypt_mnemonic("correct horse battery staple"),
+            Err(AccountError::KeystoreCostTooHigh(_))
+        ));
+
+        let mut parallel = keystore(false);
+        parallel.crypto.kdf_params.p = MAX_SCRYPT_P + 1;
+        assert!(matches!(
+            parallel.decrypt_mnemonic("correct horse battery staple"),
+            Err(AccountError::KeystoreCostTooHigh(_))
+        ));
+
+        let params = ScryptParams { log_n: MAX_SCRYPT_LOG_N + 1, ..FAST };
+        assert!(matches!(
+            Keystore::encrypt_with_params(TEST_MNEMONIC, false, Network::Bitcoin, AddressType::P2WPKH, "pw", params),
+            Err(AccountError::KeystoreCostTooHigh(_))
+        ));
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        assert!(matches!(
+            keystore(false).decrypt_mnemonic("wrong"),
+            Err(AccountError::KeystoreDecryption)
+        ));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let mut changed_type = keystore(false);
+        changed_type.address_type = AddressType::P2PKH;
+        assert!(matches!(
+            changed_type.decrypt_mnemonic("correct horse battery staple"),
+            Err(AccountError::KeystoreDecryption)
+        ));
+
+        let mut changed_flag = keystore(false);
+        changed_flag.has_passphrase = true;
+        assert!(matches!(
+            changed_flag.decrypt_mnemonic("correct horse battery staple"),
+            Err(AccountError::KeystoreDecryption)
+        ));
+
+        let mut changed_ciphertext = keystore(false);
+        let mut bytes = hex::decode(&changed_ciphertext.crypto.ciphertext).unwrap();
+        bytes[0] ^= 1;
+        changed_ciphertext.crypto.ciphertext = hex::encode(bytes);
+        assert!(matches!(
+            changed_ciphertext.decrypt_mnemonic("correct horse battery staple"),
+            Err(AccountError::KeystoreDecryption)
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let json = keystore(false).to_json().unwrap().replace("\"version\": 1", "\"version\": 2");
+
+        assert!(matches!(
+            Keystore::from_json(&json),
+            Err(AccountError::UnsupportedKeystoreVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+//! BIP-39 mnemonic generation in any length and any of the ten wordlists, from random or user
+//! supplied entropy, and validation with diagnostics for mistyped mnemonics.
+
+pub use bip39::Language;
+use bip39::Mnemonic;
+use bitcoin::hashes::{sha256, Hash};
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::errors::{AccountError, Result};
+
+/// Number of words in a BIP-39 mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WordCount {
+    #[default]
+    Words12,
+    Words15,
+    Words18,
+    Words21,
+    Words24,
+}
+
+impl WordCount {
+    pub const ALL: [WordCount; 5] = [
+        WordCount::Words12,
+        WordCount::Words15,
+        WordCount::Words18,
+        WordCount::Words21,
+        WordCount::Words24,
+    ];
+
+    pub fn from_words(words: usize) -> Result<WordCount> {
+        WordCount::ALL
+            .into_iter()
+            .find(|word_count| word_count.words() == words)
+            .ok_or(AccountError::InvalidWordCount(words))
+    }
+
+    pub fn words(self) -> usize {
+        match self {
+            WordCount::Words12 => 12,
+            WordCount::Words15 => 15,
+            WordCount::Words18 => 18,
+            WordCount::Words21 => 21,
+            WordCount::Words24 => 24,
+        }
+    }
+
+    /// Bits of entropy encoded by the words, the rest of the last word is checksum.
+    pub fn entropy_bits(self) -> usize {
+        self.words() * 32 / 3
+    }
+
+    pub fn entropy_bytes(self) -> usize {
+        self.entropy_bits() / 8
+    }
+
+    /// Rolls of a six-sided die needed to gather [`WordCount::entropy_bits`] of entropy.
+    pub fn dice_rolls(self) -> usize {
+        (self.entropy_bits() as f64 / 6f64.log2()).ceil() as usize
+    }
+}
+
+/// A new random mnemonic.
+pub fn generate(word_count: WordCount, language: Language) -> Result<Zeroizing<String>> {
+    let mut entropy = Zeroizing::new(vec![0u8; word_count.entropy_bytes()]);
+    rand::thread_rng().fill_bytes(&mut entropy);
+    from_entropy(&entropy, language)
+}
+
+/// The mnemonic encoding `entropy`, which must be 16, 20, 24, 28 or 32 bytes.
+pub fn from_entropy(entropy: &[u8], language: Language) -> Result<Zeroizing<String>> {
+    let mnemonic = Mnemonic::from_entropy_in(language, entropy)
+        .map_err(|e| AccountError::InvalidEntropy(e.to_string()))?;
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Entropy from coin flips, one bit per flip: `H` or `1` for heads, `T` or `0` for tails.
+/// Whitespace is ignored and exactly [`WordCount::entropy_bits`] flips are required.
+pub fn entropy_from_coin_flips(flips: &str, word_count: WordCount) -> Result<Zeroizing<Vec<u8>>> {
+    let mut entropy = Zeroizing::new(vec![0u8; word_count.entropy_bytes()]);
+    let mut count = 0;
+    for flip in flips.chars().filter(|c| !c.is_whitespace()) {
+        let bit = match flip {
+            'H' | 'h' | '1' => 1,
+            'T' | 't' | '0' => 0,
+            _ => return Err(AccountError::InvalidEntropy(format!("'{}' is not a coin flip", flip))),
+        };
+        if count < word_count.entropy_bits() {
+            entropy[count / 8] |= bit << (7 - count % 8);
+        }
+        count += 1;
+    }
+
+    if count != word_count.entropy_bits() {
+        return Err(AccountError::InvalidEntropy(format!(
+            "{} words need {} coin flips, got {}",
+            word_count.words(),
+            word_count.entropy_bits(),
+            count
+        )));
+    }
+    Ok(entropy)
+}
+
+/// Entropy from rolls of a six-sided die, the digits `1` to `6`, hashed with SHA-256 and
+/// truncated to the mnemonic length like Coldcard does. Whitespace is ignored and at least
+/// [`WordCount::dice_rolls`] rolls are required.
+pub fn entropy_from_dice(rolls: &str, word_count: WordCount) -> Result<Zeroizing<Vec<u8>>> {
+    let rolls = Zeroizing::new(rolls.chars().filter(|c| !c.is_whitespace()).collect::<String>());
+    if let Some(roll) = rolls.chars().find(|c| !('1'..='6').contains(c)) {
+        return Err(AccountError::InvalidEntropy(format!("'{}' is not a dice roll", roll)));
+    }
+    if rolls.len() < word_count.dice_rolls() {
+        return Err(AccountError::InvalidEntropy(format!(
+            "{} words need at least {} dice rolls, got {}",
+            word_count.words(),
+            word_count.dice_rolls(),
+            rolls.len()
+        )));
+    }
+
+    let hash = sha256::Hash::hash(rolls.as_bytes());
+    Ok(Zeroizing::new(hash.as_byte_array()[..word_count.entropy_bytes()].to_vec()))
+}
+
+/// Parses `mnemonic` in any wordlist, ignoring case and extra whitespace.
+///
+/// Failures say what is wrong: the word count, the position of the first unknown word with the
+/// closest wordlist words, or the checksum.
+pub fn parse(mnemonic: &str) -> Result<Mnemonic> {
+    let normalized = normalize(mnemonic);
+    let words = normalized.split_whitespace().collect::<Vec<_>>();
+    WordCount::from_words(words.len())?;
+
+    let languages = Language::ALL
+        .iter()
+        .copied()
+        .filter(|language| words.iter().all(|word| language.find_word(word).is_some()))
+        .collect::<Vec<_>>();
+    if languages.is_empty() {
+        // report against the wordlist the mnemonic most likely is in
+        let language = Language::ALL
+            .iter()
+            .copied()
+            .rev()
+            .max_by_key(|language| {
+                words.iter().filter(|word| language.find_word(word).is_some()).count()
+            })
+            .unwrap_or(Language::English);
+        return Err(unknown_word(&words, language));
+    }
+
+    languages
+        .into_iter()
+        .find_map(|language| Mnemonic::parse_in_normalized(language, &normalized).ok())
+        .ok_or(AccountError::InvalidMnemonicChecksum)
+}
+
+/// The BIP-32 seed of `mnemonic` with the BIP-39 `passphrase`.
+pub fn to_seed(mnemonic: &str, passphrase: &str) -> Result<Zeroizing<[u8; 64]>> {
+    Ok(Zeroizing::new(parse(mnemonic)?.to_seed(passphrase)))
+}
+
+/// The words of `language` closest to `word`, best first: words it is a 4 letter prefix of,
+/// then words at most two typos away.
+pub fn suggestions(word: &str, language: Language) -> Vec<&'static str> {
+    let word = normalize(word);
+    let typed = word.chars().collect::<Vec<_>>();
+
+    let mut scored = language
+        .word_list()
+        .iter()
+        .filter_map(|candidate| {
+            let candidate_chars = candidate.chars().collect::<Vec<_>>();
+            let distance = if typed.len() >= 4 && candidate_chars.starts_with(&typed[..4]) {
+                0
+            } else {
+                edit_distance(&typed, &candidate_chars)
+            };
+            (distance <= 2).then_some((distance, *candidate))
+        })
+        .collect::<Vec<_>>();
+    scored.sort();
+    scored.into_iter().take(5).map(|(_, candidate)| candidate).collect()
+}
+
+/// Every word completing the `prefix` words to a mnemonic with a valid checksum, e.g. the 128
+/// possible 12th words after 11 words.
+pub fn valid_final_words(prefix: &str, language: Language) -> Result<Vec<&'static str>> {
+    let normalized = normalize(prefix);
+    let words = normalized.split_whitespace().collect::<Vec<_>>();
+    WordCount::from_words(words.len() + 1)?;
+    if words.iter().any(|word| language.find_word(word).is_none()) {
+        return Err(unknown_word(&words, language));
+    }
+
+    Ok(language
+        .word_list()
+        .iter()
+        .copied()
+        .filter(|last| {
+            let candidate = Zeroizing::new(format!("{} {}", normalized.as_str(), last));
+            Mnemonic::parse_in_normalized(language, &candidate).is_ok()
+        })
+        .collect())
+}
+
+fn normalize(mnemonic: &str) -> Zeroizing<String> {
+    let mut cow = std::borrow::Cow::Owned(mnemonic.to_lowercase());
+    Mnemonic::normalize_utf8_cow(&mut cow);
+    Zeroizing::new(cow.into_owned())
+}
+
+/// Error for the first word of `words` not in `language`.
+fn unknown_word(words: &[&str], language: Language) -> AccountError {
+    let index = words
+        .iter()
+        .position(|word| language.find_word(word).is_none())
+        .unwrap_or(0);
+    AccountError::UnknownMnemonicWord {
+        position: index + 1,
+        suggestions: suggestions(words[index], language)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions and swaps of
+/// adjacent characters.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_count() {
+        assert_eq!(
+            WordCount::ALL.map(|word_count| word_count.entropy_bytes()),
+            [16, 20, 24, 28, 32]
+        );
+        assert_eq!(
+            WordCount::ALL.map(|word_count| word_count.dice_rolls()),
+            [50, 62, 75, 87, 100]
+        );
+        assert_eq!(WordCount::from_words(18).unwrap(), WordCount::Words18);
+        assert!(matches!(WordCount::from_words(13), Err(AccountError::InvalidWordCount(13))));
+    }
+
+    #[test]
+    fn test_from_entropy_vectors() {
+        // from the BIP-39 reference test vectors
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            ),
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            ),
+            (
+                "808080808080808080808080808080808080808080808080",
+                "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always",
+            ),
+            (
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+            ),
+        ];
+        for (entropy, mnemonic) in vectors {
+            let entropy = hex::decode(entropy).unwrap();
+            assert_eq!(from_entropy(&entropy, Language::English).unwrap().as_str(), mnemonic);
+        }
+
+        assert!(matches!(
+            from_entropy(&[0u8; 17], Language::English),
+            Err(AccountError::InvalidEntropy(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_every_length_and_language() {
+        for word_count in WordCount::ALL {
+            for language in Language::ALL {
+                let mnemonic = generate(word_count, *language).unwrap();
+                let parsed = Mnemonic::parse_in(*language, mnemonic.as_str()).unwrap();
+                assert_eq!(parsed.word_count(), word_count.words());
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_wordlist_round_trips() {
+        // all ten BIP-39 wordlists, Portuguese was the last one added
+        assert_eq!(Language::ALL.len(), 10);
+        assert!(Language::ALL.contains(&Language::Portuguese));
+
+        for language in Language::ALL {
+            for word_count in WordCount::ALL {
+                let mnemonic = generate(word_count, *language).unwrap();
+                // simplified and traditional Chinese share characters, so the detected
+                // wordlist is not compared, only that the mnemonic parses and gives the same seed
+                let parsed = parse(&mnemonic).unwrap();
+                assert_eq!(parsed.word_count(), word_count.words());
+                let expected = Mnemonic::parse_in(*language, mnemonic.as_str()).unwrap();
+                assert_eq!(to_seed(&mnemonic, "").unwrap().as_slice(), expected.to_seed("").as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn test_entropy_from_coin_flips() {
+        let flips = "HT".repeat(64);
+        let entropy = entropy_from_coin_flips(&flips, WordCount::Words12).unwrap();
+        assert_eq!(hex::encode(entropy.as_slice()), "aa".repeat(16));
+
+        let flips = "0111 1111 ".repeat(16);
+        let entropy = entropy_from_coin_flips(&flips, WordCount::Words12).unwrap();
+        assert_eq!(
+            from_entropy(&entropy, Language::English).unwrap().as_str(),
+            "legal winner thank year wave sausage worth useful legal winner thank yellow"
+        );
+
+        assert!(entropy_from_coin_flips(&"H".repeat(127), WordCount::Words12).is_err());
+        assert!(entropy_from_coin_flips(&"H".repeat(129), WordCount::Words12).is_err());
+        assert!(entropy_from_coin_flips(&format!("{}X", "H".repeat(127)), WordCount::Words12).is_err());
+    }
+
+    #[test]
+    fn test_entropy_from_dice() {
+        let rolls = "123456".repeat(17);
+        let entropy = entropy_from_dice(&rolls, WordCount::Words24).unwrap();
+        let hash = sha256::Hash::hash(rolls.as_bytes());
+        assert_eq!(entropy.as_slice(), hash.as_byte_array());
+
+        let entropy_12 = entropy_from_dice(&rolls, WordCount::Words12).unwrap();
+        assert_eq!(entropy_12.as_slice(), &hash.as_byte_array()[..16]);
+
+        // whitespace does not change the result
+        let spaced = rolls.chars().map(|c| format!("{} ", c)).collect::<String>();
+        assert_eq!(entropy_from_dice(&spaced, WordCount::Words24).unwrap(), entropy);
+
+        assert!(entropy_from_dice(&"1".repeat(99), WordCount::Words24).is_err());
+        assert!(entropy_from_dice(&"7".repeat(100), WordCount::Words24).is_err());
+    }
+
+    const MNEMONIC: &str = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    #[test]
+    fn test_parse() {
+        let mnemonic =
+            parse("  Wish film PEASANT much sure thought speed print napkin hard crumble   envelope ").unwrap();
+        assert_eq!(mnemonic.to_string(), MNEMONIC);
+        assert_eq!(mnemonic.language(), Language::English);
+
+        let japanese = generate(WordCount::Words12, Language::Japanese).unwrap();
+        assert_eq!(parse(&japanese).unwrap().language(), Language::Japanese);
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        assert!(matches!(parse("wish film peasant"), Err(AccountError::InvalidWordCount(3))));
+
+        let typo = MNEMONIC.replace("peasant", "paesant");
+        match parse(&typo) {
+            Err(AccountError::UnknownMnemonicWord { position, suggestions }) => {
+                assert_eq!(position, 3);
+                assert_eq!(suggestions[0], "peasant");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let swapped = MNEMONIC.replace("wish film", "film wish");
+        assert!(matches!(parse(&swapped), Err(AccountError::InvalidMnemonicChecksum)));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        // unique 4 letter prefix
+        assert_eq!(suggestions("envel", Language::English)[0], "envelope");
+        // one substitution away
+        assert!(suggestions("crumbla", Language::English).contains(&"crumble"));
+        // too far from anything
+        assert!(suggestions("xyzzyq", Language::English).is_empty());
+    }
+
+    #[test]
+    fn test_valid_final_words() {
+        let prefix = MNEMONIC.rsplit_once(' ').unwrap().0;
+        let words = valid_final_words(prefix, Language::English).unwrap();
+        assert_eq!(words.len(), 128);
+        assert!(words.contains(&"envelope"));
+
+        let prefix = vec!["abandon"; 23].join(" ");
+        let words = valid_final_words(&prefix, Language::English).unwrap();
+        assert_eq!(words.len(), 8);
+        assert!(words.contains(&"art"));
+
+        assert!(matches!(
+            valid_final_words("abandon abandon", Language::English),
+            Err(AccountError::InvalidWordCount(3))
+        ));
+    }
+}
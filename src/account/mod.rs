@@ -1,3 +1,12 @@
+#[allow(clippy::module_inception)]
 pub mod account;
 pub mod address_type;
+pub mod bip38;
+pub mod bip85;
+pub mod discovery;
+pub mod electrum;
+pub mod keystore;
+pub mod mnemonic;
+pub mod slip39;
+pub mod wallet;
 pub mod xyz_pub;
@@ -0,0 +1,806 @@
+//! SLIP-39 Shamir backups: a master secret split into groups of mnemonic shares, any
+//! `group_threshold` groups with `threshold` shares each recover it.
+//!
+//! The master secret is used as the BIP-32 seed, like Trezor does. To back up a BIP-39 wallet
+//! split its seed, see [`crate::account::mnemonic::to_seed`].
+
+use std::collections::BTreeMap;
+
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use rand::{Rng, RngCore};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::errors::{AccountError, Result};
+
+const RADIX_BITS: usize = 10;
+/// identifier, extendable flag and iteration exponent, then the group and member parameters
+const HEADER_WORDS: usize = 4;
+const CHECKSUM_WORDS: usize = 3;
+const MIN_MNEMONIC_WORDS: usize = 20;
+const MIN_SECRET_BYTES: usize = 16;
+const MAX_SHARE_COUNT: u8 = 16;
+
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const DIGEST_BYTES: usize = 4;
+
+const BASE_ITERATION_COUNT: u32 = 10_000;
+const ROUND_COUNT: u8 = 4;
+
+/// Threshold and number of member shares of one group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupSpec {
+    pub threshold: u8,
+    pub count: u8,
+}
+
+impl GroupSpec {
+    pub fn new(threshold: u8, count: u8) -> Self {
+        GroupSpec { threshold, count }
+    }
+}
+
+/// Splits `master_secret` into mnemonic shares, one list per group of `groups`.
+///
+/// `master_secret` must be at least 16 bytes and an even number of bytes. It is encrypted with
+/// `passphrase` first, using `10000 << iteration_exponent` PBKDF2 iterations. Extendable
+/// backups can later get more shares with the same identifier.
+pub fn split(
+    master_secret: &[u8],
+    passphrase: &str,
+    group_threshold: u8,
+    groups: &[GroupSpec],
+    iteration_exponent: u8,
+    extendable: bool,
+) -> Result<Vec<Vec<Zeroizing<String>>>> {
+    if master_secret.len() < MIN_SECRET_BYTES || !master_secret.len().is_multiple_of(2) {
+        return Err(invalid(format!(
+            "master secret must be an even number of bytes, at least {}",
+            MIN_SECRET_BYTES
+        )));
+    }
+    if iteration_exponent > 15 {
+        return Err(invalid("iteration exponent must be at most 15"));
+    }
+    if groups.is_empty() || groups.len() > MAX_SHARE_COUNT as usize {
+        return Err(invalid(format!("between 1 and {} groups are required", MAX_SHARE_COUNT)));
+    }
+    if group_threshold == 0 || group_threshold as usize > groups.len() {
+        return Err(invalid("group threshold must be between 1 and the number of groups"));
+    }
+    for group in groups {
+        if group.threshold == 0 || group.threshold > group.count || group.count > MAX_SHARE_COUNT {
+            return Err(invalid(format!(
+                "group threshold {} of {} shares is not possible",
+                group.threshold, group.count
+            )));
+        }
+        if group.threshold == 1 && group.count > 1 {
+            return Err(invalid("use a single share for groups with threshold 1"));
+        }
+    }
+
+    let identifier = rand::thread_rng().gen_range(0..1u16 << 15);
+    let encrypted = feistel(
+        master_secret,
+        &check_passphrase(passphrase)?,
+        iteration_exponent,
+        identifier,
+        extendable,
+        false,
+    );
+
+    let group_secrets = split_secret(group_threshold, groups.len() as u8, &encrypted)?;
+    groups
+        .iter()
+        .zip(group_secrets)
+        .map(|(group, (group_index, group_secret))| {
+            split_secret(group.threshold, group.count, &group_secret)?
+                .into_iter()
+                .map(|(member_index, value)| {
+                    Share {
+                        identifier,
+                        extendable,
+                        iteration_exponent,
+                        group_index,
+                        group_threshold,
+                        group_count: groups.len() as u8,
+                        member_index,
+                        member_threshold: group.threshold,
+                        value,
+                    }
+                    .to_mnemonic()
+                })
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect()
+}
+
+/// Recovers the master secret from the `mnemonics` shares of one backup: shares of exactly
+/// `group_threshold` groups, and exactly the member threshold of shares in each of them.
+pub fn combine<S: AsRef<str>>(mnemonics: &[S], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let shares = mnemonics
+        .iter()
+        .map(|mnemonic| Share::parse(mnemonic.as_ref()))
+        .collect::<Result<Vec<_>>>()?;
+    let first = shares.first().ok_or_else(|| invalid("no shares"))?;
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in &shares {
+        if share.identifier != first.identifier
+            || share.extendable != first.extendable
+            || share.iteration_exponent != first.iteration_exponent
+            || share.group_threshold != first.group_threshold
+            || share.group_count != first.group_count
+            || share.value.len() != first.value.len()
+        {
+            return Err(invalid("shares are from different backups"));
+        }
+        let group = groups.entry(share.group_index).or_default();
+        if group.iter().any(|other| other.member_threshold != share.member_threshold) {
+            return Err(invalid(format!("group {} has mixed thresholds", share.group_index + 1)));
+        }
+        match group.iter().find(|other| other.member_index == share.member_index) {
+            Some(other) if other.value != share.value => {
+                return Err(invalid("two different shares with the same index"))
+            }
+            Some(_) => {}
+            None => group.push(share),
+        }
+    }
+
+    // like the reference implementation, exactly the threshold number of groups and of shares
+    // in each group must be given
+    if groups.len() != first.group_threshold as usize {
+        return Err(invalid(format!(
+            "{} groups are required, got {}",
+            first.group_threshold,
+            groups.len()
+        )));
+    }
+    let mut group_secrets = Vec::new();
+    for (group_index, members) in &groups {
+        let threshold = members[0].member_threshold;
+        if members.len() != threshold as usize {
+            return Err(invalid(format!(
+                "group {} needs {} shares, got {}",
+                group_index + 1,
+                threshold,
+                members.len()
+            )));
+        }
+        let members = members
+            .iter()
+            .map(|share| (share.member_index, share.value.clone()))
+            .collect::<Vec<_>>();
+        group_secrets.push((*group_index, recover_secret(threshold, &members)?));
+    }
+
+    let encrypted = recover_secret(first.group_threshold, &group_secrets)?;
+    Ok(feistel(
+        &encrypted,
+        &check_passphrase(passphrase)?,
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+        true,
+    ))
+}
+
+fn invalid(message: impl Into<String>) -> AccountError {
+    AccountError::InvalidSlip39(message.into())
+}
+
+/// SLIP-39 passphrases are limited to printable ASCII.
+fn check_passphrase(passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    if !passphrase.bytes().all(|byte| (32..=126).contains(&byte)) {
+        return Err(invalid("passphrase must be printable ASCII"));
+    }
+    Ok(Zeroizing::new(passphrase.as_bytes().to_vec()))
+}
+
+/// One parsed share mnemonic.
+struct Share {
+    identifier: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Zeroizing<Vec<u8>>,
+}
+
+impl Share {
+    fn parse(mnemonic: &str) -> Result<Share> {
+        let mnemonic = Zeroizing::new(mnemonic.to_lowercase());
+        let words = mnemonic
+            .split_whitespace()
+            .enumerate()
+            .map(|(index, word)| {
+                WORDLIST
+                    .binary_search(&word)
+                    .map(|index| index as u16)
+                    .map_err(|_| invalid(format!("unknown word at position {}", index + 1)))
+            })
+            .collect::<Result<Vec<u16>>>()
+            .map(Zeroizing::new)?;
+        if words.len() < MIN_MNEMONIC_WORDS {
+            return Err(invalid(format!("shares have at least {} words", MIN_MNEMONIC_WORDS)));
+        }
+
+        let extendable = (words[1] >> 4) & 1 == 1;
+        if rs1024_polymod(customization(extendable), &words) != 1 {
+            return Err(invalid("invalid checksum"));
+        }
+
+        let value_words = &words[HEADER_WORDS..words.len() - CHECKSUM_WORDS];
+        let padding = (RADIX_BITS * value_words.len()) % 16;
+        if padding > 8 {
+            return Err(invalid("invalid share length"));
+        }
+        let value = from_words(value_words, padding)?;
+
+        let header = (words[2] as u32) << 10 | words[3] as u32;
+        let group_threshold = (header >> 12 & 0xf) as u8 + 1;
+        let group_count = (header >> 8 & 0xf) as u8 + 1;
+        if group_threshold > group_count {
+            return Err(invalid("group threshold is greater than the group count"));
+        }
+        let group_index = (header >> 16) as u8;
+        if group_index >= group_count {
+            return Err(invalid("group index is not below the group count"));
+        }
+
+        Ok(Share {
+            identifier: ((words[0] as u32) << 5 | (words[1] >> 5) as u32) as u16,
+            extendable,
+            iteration_exponent: (words[1] & 0xf) as u8,
+            group_index,
+            group_threshold,
+            group_count,
+            member_index: (header >> 4 & 0xf) as u8,
+            member_threshold: (header & 0xf) as u8 + 1,
+            value,
+        })
+    }
+
+    fn to_mnemonic(&self) -> Result<Zeroizing<String>> {
+        let mut words = Zeroizing::new(vec![
+            self.identifier >> 5,
+            (self.identifier & 0x1f) << 5 | u16::from(self.extendable) << 4 | self.iteration_exponent as u16,
+            (self.group_index as u16) << 6
+                | (self.group_threshold as u16 - 1) << 2
+                | (self.group_count as u16 - 1) >> 2,
+            ((self.group_count as u16 - 1) & 0x3) << 8
+                | (self.member_index as u16) << 4
+                | (self.member_threshold as u16 - 1),
+        ]);
+        words.extend(to_words(&self.value));
+
+        let customization = customization(self.extendable);
+        let mut values = words.to_vec();
+        values.extend([0; CHECKSUM_WORDS]);
+        let checksum = rs1024_polymod(customization, &values) ^ 1;
+        words.extend((0..CHECKSUM_WORDS).map(|i| (checksum >> (RADIX_BITS * (2 - i)) & 0x3ff) as u16));
+
+        Ok(Zeroizing::new(
+            words.iter().map(|word| WORDLIST[*word as usize]).collect::<Vec<_>>().join(" "),
+        ))
+    }
+}
+
+/// Big-endian 10 bit words of `value`, left padded with zero bits.
+fn to_words(value: &[u8]) -> Vec<u16> {
+    let bits = value.len() * 8;
+    let count = bits.div_ceil(RADIX_BITS);
+    let padding = count * RADIX_BITS - bits;
+
+    let bit = |i: usize| -> u16 {
+        if i < padding {
+            0
+        } else {
+            let i = i - padding;
+            (value[i / 8] >> (7 - i % 8) & 1) as u16
+        }
+    };
+    (0..count)
+        .map(|word| (0..RADIX_BITS).fold(0, |acc, i| acc << 1 | bit(word * RADIX_BITS + i)))
+        .collect()
+}
+
+/// Inverse of [`to_words`], the `padding` leading bits must be zero.
+fn from_words(words: &[u16], padding: usize) -> Result<Zeroizing<Vec<u8>>> {
+    let bit = |i: usize| words[i / RADIX_BITS] >> (RADIX_BITS - 1 - i % RADIX_BITS) & 1;
+    if (0..padding).any(|i| bit(i) != 0) {
+        return Err(invalid("invalid padding"));
+    }
+
+    let bytes = (words.len() * RADIX_BITS - padding) / 8;
+    Ok(Zeroizing::new(
+        (0..bytes)
+            .map(|byte| (0..8).fold(0u8, |acc, i| acc << 1 | bit(padding + byte * 8 + i) as u8))
+            .collect(),
+    ))
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable {
+        b"shamir_extendable"
+    } else {
+        b"shamir"
+    }
+}
+
+fn rs1024_polymod(customization: &[u8], words: &[u16]) -> u32 {
+    const GENERATOR: [u32; 10] = [
+        0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009, 0x1c0c2412, 0x38086c24, 0x3090fc48,
+        0x21b1f890, 0x3f3f120,
+    ];
+
+    let mut checksum = 1u32;
+    for value in customization.iter().map(|byte| *byte as u32).chain(words.iter().map(|word| *word as u32)) {
+        let top = checksum >> 20;
+        checksum = (checksum & 0xfffff) << 10 ^ value;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if top >> i & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// The four round Feistel network encrypting the master secret, or decrypting with `decrypt`.
+fn feistel(
+    secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    decrypt: bool,
+) -> Zeroizing<Vec<u8>> {
+    let half = secret.len() / 2;
+    let mut left = Zeroizing::new(secret[..half].to_vec());
+    let mut right = Zeroizing::new(secret[half..].to_vec());
+
+    let mut salt_prefix = Vec::new();
+    if !extendable {
+        salt_prefix.extend_from_slice(b"shamir");
+        salt_prefix.extend_from_slice(&identifier.to_be_bytes());
+    }
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / ROUND_COUNT as u32;
+
+    let rounds: Vec<u8> = if decrypt {
+        (0..ROUND_COUNT).rev().collect()
+    } else {
+        (0..ROUND_COUNT).collect()
+    };
+    for round in rounds {
+        let mut password = Zeroizing::new(vec![round]);
+        password.extend_from_slice(passphrase);
+        let mut salt = salt_prefix.clone();
+        salt.extend_from_slice(&right);
+
+        let mut round_key = Zeroizing::new(vec![0u8; half]);
+        pbkdf2_hmac::<Sha256>(&password, &salt, iterations, &mut round_key);
+        let next = Zeroizing::new(left.iter().zip(round_key.iter()).map(|(l, k)| l ^ k).collect::<Vec<_>>());
+        left = std::mem::replace(&mut right, next);
+    }
+
+    right.extend_from_slice(&left);
+    right
+}
+
+/// Shares of `secret` at x = 0, 1, ..., `count - 1`, any `threshold` of which recover it.
+fn split_secret(threshold: u8, count: u8, secret: &[u8]) -> Result<Vec<(u8, Zeroizing<Vec<u8>>)>> {
+    if threshold == 1 {
+        return Ok((0..count).map(|index| (index, Zeroizing::new(secret.to_vec()))).collect());
+    }
+
+    let mut rng = rand::thread_rng();
+    let random_count = threshold - 2;
+    let mut shares = (0..random_count)
+        .map(|index| {
+            let mut value = Zeroizing::new(vec![0u8; secret.len()]);
+            rng.fill_bytes(&mut value);
+            (index, value)
+        })
+        .collect::<Vec<_>>();
+
+    let mut digest_share = Zeroizing::new(vec![0u8; secret.len()]);
+    rng.fill_bytes(&mut digest_share[DIGEST_BYTES..]);
+    let digest = secret_digest(&digest_share[DIGEST_BYTES..], secret);
+    digest_share[..DIGEST_BYTES].copy_from_slice(&digest);
+
+    let mut base = shares.clone();
+    base.push((DIGEST_INDEX, digest_share));
+    base.push((SECRET_INDEX, Zeroizing::new(secret.to_vec())));
+    for index in random_count..count {
+        shares.push((index, interpolate(&base, index)));
+    }
+    Ok(shares)
+}
+
+/// The secret of `threshold` distinct `shares`, checked against the digest share.
+fn recover_secret(threshold: u8, shares: &[(u8, Zeroizing<Vec<u8>>)]) -> Result<Zeroizing<Vec<u8>>> {
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+
+    let secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    if secret_digest(&digest_share[DIGEST_BYTES..], &secret) != digest_share[..DIGEST_BYTES] {
+        return Err(invalid("digest mismatch, the shares do not belong together"));
+    }
+    Ok(secret)
+}
+
+fn secret_digest(random: &[u8], secret: &[u8]) -> [u8; DIGEST_BYTES] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random).expect("hmac takes any key length");
+    mac.update(secret);
+    let mut digest = [0u8; DIGEST_BYTES];
+    digest.copy_from_slice(&mac.finalize().into_bytes()[..DIGEST_BYTES]);
+    digest
+}
+
+/// Lagrange interpolation of `shares` at `x` in GF(256) with the Rijndael polynomial.
+fn interpolate(shares: &[(u8, Zeroizing<Vec<u8>>)], x: u8) -> Zeroizing<Vec<u8>> {
+    if let Some((_, value)) = shares.iter().find(|(share_x, _)| *share_x == x) {
+        return value.clone();
+    }
+
+    let (exp, log) = gf256_tables();
+    let log_product: u32 = shares.iter().map(|(share_x, _)| log[(share_x ^ x) as usize] as u32).sum();
+
+    let mut result = Zeroizing::new(vec![0u8; shares[0].1.len()]);
+    for (share_x, value) in shares {
+        let others: u32 = shares
+            .iter()
+            .filter(|(other_x, _)| other_x != share_x)
+            .map(|(other_x, _)| log[(share_x ^ other_x) as usize] as u32)
+            .sum();
+        // shifted by multiples of 255 to stay positive
+        let log_basis = (log_product + 255 * shares.len() as u32
+            - log[(share_x ^ x) as usize] as u32
+            - others)
+            % 255;
+
+        for (result, byte) in result.iter_mut().zip(value.iter()) {
+            if *byte != 0 {
+                *result ^= exp[((log[*byte as usize] as u32 + log_basis) % 255) as usize];
+            }
+        }
+    }
+    result
+}
+
+/// Exponent and logarithm tables of GF(256) for the generator 3.
+fn gf256_tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0u8; 255];
+    let mut log = [0u8; 256];
+    let mut value = 1u16;
+    for (i, entry) in exp.iter_mut().enumerate() {
+        *entry = value as u8;
+        log[value as usize] = i as u8;
+        value ^= value << 1;
+        if value & 0x100 != 0 {
+            value ^= 0x11b;
+        }
+    }
+    (exp, log)
+}
+
+/// The SLIP-39 wordlist, sorted, every word has a unique 4 letter prefix.
+const WORDLIST: [&str; 1024] = [
+    "academic", "acid", "acne", "acquire", "acrobat", "activity", "actress", "adapt", "adequate",
+    "adjust", "admit", "adorn", "adult", "advance", "advocate", "afraid", "again", "agency",
+    "agree", "aide", "aircraft", "airline", "airport", "ajar", "alarm", "album", "alcohol",
+    "alien", "alive", "alpha", "already", "alto", "aluminum", "always", "amazing", "ambition",
+    "amount", "amuse", "analysis", "anatomy", "ancestor", "ancient", "angel", "angry", "animal",
+    "answer", "antenna", "anxiety", "apart", "aquatic", "arcade", "arena", "argue", "armed",
+    "artist", "artwork", "aspect", "auction", "august", "aunt", "average", "aviation", "avoid",
+    "award", "away", "axis", "axle", "beam", "beard", "beaver", "become", "bedroom", "behavior",
+    "being", "believe", "belong", "benefit", "best", "beyond", "bike", "biology", "birthday",
+    "bishop", "black", "blanket", "blessing", "blimp", "blind", "blue", "body", "bolt", "boring",
+    "born", "both", "boundary", "bracelet", "branch", "brave", "breathe", "briefing", "broken",
+    "brother", "browser", "bucket", "budget", "building", "bulb", "bulge", "bumpy", "bundle",
+    "burden", "burning", "busy", "buyer", "cage", "calcium", "camera", "campus", "canyon",
+    "capacity", "capital", "capture", "carbon", "cards", "careful", "cargo", "carpet", "carve",
+    "category", "cause", "ceiling", "center", "ceramic", "champion", "change", "charity", "check",
+    "chemical", "chest", "chew", "chubby", "cinema", "civil", "class", "clay", "cleanup", "client",
+    "climate", "clinic", "clock", "clogs", "closet", "clothes", "club", "cluster", "coal",
+    "coastal", "coding", "column", "company", "corner", "costume", "counter", "course", "cover",
+    "cowboy", "cradle", "craft", "crazy", "credit", "cricket", "criminal", "crisis", "critical",
+    "crowd", "crucial", "crunch", "crush", "crystal", "cubic", "cultural", "curious", "curly",
+    "custody", "cylinder", "daisy", "damage", "dance", "darkness", "database", "daughter",
+    "deadline", "deal", "debris", "debut", "decent", "decision", "declare", "decorate", "decrease",
+    "deliver", "demand", "density", "deny", "depart", "depend", "depict", "deploy", "describe",
+    "desert", "desire", "desktop", "destroy", "detailed", "detect", "device", "devote", "diagnose",
+    "dictate", "diet", "dilemma", "diminish", "dining", "diploma", "disaster", "discuss",
+    "disease", "dish", "dismiss", "display", "distance", "dive", "divorce", "document", "domain",
+    "domestic", "dominant", "dough", "downtown", "dragon", "dramatic", "dream", "dress", "drift",
+    "drink", "drove", "drug", "dryer", "duckling", "duke", "duration", "dwarf", "dynamic", "early",
+    "earth", "easel", "easy", "echo", "eclipse", "ecology", "edge", "editor", "educate", "either",
+    "elbow", "elder", "election", "elegant", "element", "elephant", "elevator", "elite", "else",
+    "email", "emerald", "emission", "emperor", "emphasis", "employer", "empty", "ending",
+    "endless", "endorse", "enemy", "energy", "enforce", "engage", "enjoy", "enlarge", "entrance",
+    "envelope", "envy", "epidemic", "episode", "equation", "equip", "eraser", "erode", "escape",
+    "estate", "estimate", "evaluate", "evening", "evidence", "evil", "evoke", "exact", "example",
+    "exceed", "exchange", "exclude", "excuse", "execute", "exercise", "exhaust", "exotic",
+    "expand", "expect", "explain", "express", "extend", "extra", "eyebrow", "facility", "fact",
+    "failure", "faint", "fake", "false", "family", "famous", "fancy", "fangs", "fantasy", "fatal",
+    "fatigue", "favorite", "fawn", "fiber", "fiction", "filter", "finance", "findings", "finger",
+    "firefly", "firm", "fiscal", "fishing", "fitness", "flame", "flash", "flavor", "flea",
+    "flexible", "flip", "float", "floral", "fluff", "focus", "forbid", "force", "forecast",
+    "forget", "formal", "fortune", "forward", "founder", "fraction", "fragment", "frequent",
+    "freshman", "friar", "fridge", "friendly", "frost", "froth", "frozen", "fumes", "funding",
+    "furl", "fused", "galaxy", "game", "garbage", "garden", "garlic", "gasoline", "gather",
+    "general", "genius", "genre", "genuine", "geology", "gesture", "glad", "glance", "glasses",
+    "glen", "glimpse", "goat", "golden", "graduate", "grant", "grasp", "gravity", "gray",
+    "greatest", "grief", "grill", "grin", "grocery", "gross", "group", "grownup", "grumpy",
+    "guard", "guest", "guilt", "guitar", "gums", "hairy", "hamster", "hand", "hanger", "harvest",
+    "have", "havoc", "hawk", "hazard", "headset", "health", "hearing", "heat", "helpful", "herald",
+    "herd", "hesitate", "hobo", "holiday", "holy", "home", "hormone", "hospital", "hour", "huge",
+    "human", "humidity", "hunting", "husband", "hush", "husky", "hybrid", "idea", "identify",
+    "idle", "image", "impact", "imply", "improve", "impulse", "include", "income", "increase",
+    "index", "indicate", "industry", "infant", "inform", "inherit", "injury", "inmate", "insect",
+    "inside", "install", "intend", "intimate", "invasion", "involve", "iris", "island", "isolate",
+    "item", "ivory", "jacket", "jerky", "jewelry", "join", "judicial", "juice", "jump", "junction",
+    "junior", "junk", "jury", "justice", "kernel", "keyboard", "kidney", "kind", "kitchen",
+    "knife", "knit", "laden", "ladle", "ladybug", "lair", "lamp", "language", "large", "laser",
+    "laundry", "lawsuit", "leader", "leaf", "learn", "leaves", "lecture", "legal", "legend",
+    "legs", "lend", "length", "level", "liberty", "library", "license", "lift", "likely", "lilac",
+    "lily", "lips", "liquid", "listen", "literary", "living", "lizard", "loan", "lobe", "location",
+    "losing", "loud", "loyalty", "luck", "lunar", "lunch", "lungs", "luxury", "lying", "lyrics",
+    "machine", "magazine", "maiden", "mailman", "main", "makeup", "making", "mama", "manager",
+    "mandate", "mansion", "manual", "marathon", "march", "market", "marvel", "mason", "material",
+    "math", "maximum", "mayor", "meaning", "medal", "medical", "member", "memory", "mental",
+    "merchant", "merit", "method", "metric", "midst", "mild", "military", "mineral", "minister",
+    "miracle", "mixed", "mixture", "mobile", "modern", "modify", "moisture", "moment", "morning",
+    "mortgage", "mother", "mountain", "mouse", "move", "much", "mule", "multiple", "muscle",
+    "museum", "music", "mustang", "nail", "national", "necklace", "negative", "nervous", "network",
+    "news", "nuclear", "numb", "numerous", "nylon", "oasis", "obesity", "object", "observe",
+    "obtain", "ocean", "often", "olympic", "omit", "oral", "orange", "orbit", "order", "ordinary",
+    "organize", "ounce", "oven", "overall", "owner", "paces", "pacific", "package", "paid",
+    "painting", "pajamas", "pancake", "pants", "papa", "paper", "parcel", "parking", "party",
+    "patent", "patrol", "payment", "payroll", "peaceful", "peanut", "peasant", "pecan", "penalty",
+    "pencil", "percent", "perfect", "permit", "petition", "phantom", "pharmacy", "photo", "phrase",
+    "physics", "pickup", "picture", "piece", "pile", "pink", "pipeline", "pistol", "pitch",
+    "plains", "plan", "plastic", "platform", "playoff", "pleasure", "plot", "plunge", "practice",
+    "prayer", "preach", "predator", "pregnant", "premium", "prepare", "presence", "prevent",
+    "priest", "primary", "priority", "prisoner", "privacy", "prize", "problem", "process",
+    "profile", "program", "promise", "prospect", "provide", "prune", "public", "pulse", "pumps",
+    "punish", "puny", "pupal", "purchase", "purple", "python", "quantity", "quarter", "quick",
+    "quiet", "race", "racism", "radar", "railroad", "rainbow", "raisin", "random", "ranked",
+    "rapids", "raspy", "reaction", "realize", "rebound", "rebuild", "recall", "receiver",
+    "recover", "regret", "regular", "reject", "relate", "remember", "remind", "remove", "render",
+    "repair", "repeat", "replace", "require", "rescue", "research", "resident", "response",
+    "result", "retailer", "retreat", "reunion", "revenue", "review", "reward", "rhyme", "rhythm",
+    "rich", "rival", "river", "robin", "rocky", "romantic", "romp", "roster", "round", "royal",
+    "ruin", "ruler", "rumor", "sack", "safari", "salary", "salon", "salt", "satisfy", "satoshi",
+    "saver", "says", "scandal", "scared", "scatter", "scene", "scholar", "science", "scout",
+    "scramble", "screw", "script", "scroll", "seafood", "season", "secret", "security", "segment",
+    "senior", "shadow", "shaft", "shame", "shaped", "sharp", "shelter", "sheriff", "short",
+    "should", "shrimp", "sidewalk", "silent", "silver", "similar", "simple", "single", "sister",
+    "skin", "skunk", "slap", "slavery", "sled", "slice", "slim", "slow", "slush", "smart", "smear",
+    "smell", "smirk", "smith", "smoking", "smug", "snake", "snapshot", "sniff", "society",
+    "software", "soldier", "solution", "soul", "source", "space", "spark", "speak", "species",
+    "spelling", "spend", "spew", "spider", "spill", "spine", "spirit", "spit", "spray", "sprinkle",
+    "square", "squeeze", "stadium", "staff", "standard", "starting", "station", "stay", "steady",
+    "step", "stick", "stilt", "story", "strategy", "strike", "style", "subject", "submit", "sugar",
+    "suitable", "sunlight", "superior", "surface", "surprise", "survive", "sweater", "swimming",
+    "swing", "switch", "symbolic", "sympathy", "syndrome", "system", "tackle", "tactics",
+    "tadpole", "talent", "task", "taste", "taught", "taxi", "teacher", "teammate", "teaspoon",
+    "temple", "tenant", "tendency", "tension", "terminal", "testify", "texture", "thank", "that",
+    "theater", "theory", "therapy", "thorn", "threaten", "thumb", "thunder", "ticket", "tidy",
+    "timber", "timely", "ting", "tofu", "together", "tolerate", "total", "toxic", "tracks",
+    "traffic", "training", "transfer", "trash", "traveler", "treat", "trend", "trial", "tricycle",
+    "trip", "triumph", "trouble", "true", "trust", "twice", "twin", "type", "typical", "ugly",
+    "ultimate", "umbrella", "uncover", "undergo", "unfair", "unfold", "unhappy", "union",
+    "universe", "unkind", "unknown", "unusual", "unwrap", "upgrade", "upstairs", "username",
+    "usher", "usual", "valid", "valuable", "vampire", "vanish", "various", "vegan", "velvet",
+    "venture", "verdict", "verify", "very", "veteran", "vexed", "victim", "video", "view",
+    "vintage", "violence", "viral", "visitor", "visual", "vitamins", "vocal", "voice", "volume",
+    "voter", "voting", "walnut", "warmth", "warn", "watch", "wavy", "wealthy", "weapon", "webcam",
+    "welcome", "welfare", "western", "width", "wildlife", "window", "wine", "wireless", "wisdom",
+    "withdraw", "wits", "wolf", "woman", "work", "worthy", "wrap", "wrist", "writing", "wrote",
+    "year", "yelp", "yield", "yoga", "zero",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(mnemonics: &[&str], secret: &str) {
+        assert_eq!(hex::encode(combine(mnemonics, "TREZOR").unwrap().as_slice()), secret);
+    }
+
+    fn rejected(mnemonics: &[&str]) {
+        assert!(matches!(combine(mnemonics, "TREZOR"), Err(AccountError::InvalidSlip39(_))));
+    }
+
+    // shares of the SLIP-39 test vectors with groups, 2 of 4 groups needed
+    const GROUP_0: &str = "eraser senior acrobat romp bishop medical gesture pumps secret alive ultimate quarter priest subject class dictate spew material endless market";
+    const GROUP_1: &str = "eraser senior beard romp adorn nuclear spill corner cradle style ancient family general leader ambition exchange unusual garlic promise voice";
+    const GROUP_2: [&str; 3] = [
+        "eraser senior ceramic snake clay various huge numb argue hesitate auction category timber browser greatest hanger petition script leaf pickup",
+        "eraser senior ceramic shaft dynamic become junior wrist silver peasant force math alto coal amazing segment yelp velvet image paces",
+        "eraser senior ceramic round column hawk trust auction smug shame alive greatest sheriff living perfect corner chest sled fumes adequate",
+    ];
+    const GROUP_3: [&str; 2] = [
+        "eraser senior decision smug corner ruin rescue cubic angel tackle skin skunk program roster trash rumor slush angel flea amazing",
+        "eraser senior decision roster beard treat identify grumpy salt index fake aviation theater cubic bike cause research dragon emphasis counter",
+    ];
+    const GROUP_SECRET: &str = "7c3397a292a5941682d7a4ae2d898d11";
+
+    #[test]
+    fn test_trezor_vectors() {
+        // from the SLIP-39 test vectors, passphrase "TREZOR"
+        vector(
+            &["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"],
+            "bb54aac4b89dc868ba37d9cc21b2cece",
+        );
+        vector(
+            &[
+                "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+                "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+            ],
+            "b43ceb7e57a0ea8766221624d01b0864",
+        );
+        vector(&[GROUP_1, GROUP_2[0], GROUP_2[1], GROUP_2[2]], GROUP_SECRET);
+        vector(&[GROUP_3[1], GROUP_2[1], GROUP_3[0], GROUP_2[0], GROUP_2[2]], GROUP_SECRET);
+        vector(&[GROUP_1, GROUP_0], GROUP_SECRET);
+        vector(
+            &["theory painting academic academic armed sweater year military elder discuss acne wildlife boring employer fused large satoshi bundle carbon diagnose anatomy hamster leaves tracks paces beyond phantom capital marvel lips brave detect luck"],
+            "989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92",
+        );
+        vector(
+            &[
+                "humidity disease academic always aluminum jewelry energy woman receiver strategy amuse duckling lying evidence network walnut tactics forget hairy rebound impulse brother survive clothes stadium mailman rival ocean reward venture always armed unwrap",
+                "humidity disease academic agency actress jacket gross physics cylinder solution fake mortgage benefit public busy prepare sharp friar change work slow purchase ruler again tricycle involve viral wireless mixture anatomy desert cargo upgrade",
+            ],
+            "c938b319067687e990e05e0da0ecce1278f75ff58d9853f19dcaeed5de104aae",
+        );
+        vector(
+            &["testify swimming academic academic column loyalty smear include exotic bedroom exotic wrist lobe cover grief golden smart junior estimate learn"],
+            "1679b4516e0ee5954351d288a838f45e",
+        );
+        vector(
+            &[
+                "enemy favorite academic acid cowboy phrase havoc level response walnut budget painting inside trash adjust froth kitchen learn tidy punish",
+                "enemy favorite academic always academic sniff script carpet romp kind promise scatter center unfair training emphasis evening belong fake enforce",
+            ],
+            "48b1a4b80b8c209ad42c33672bdaa428",
+        );
+        vector(
+            &["impulse calcium academic academic alcohol sugar lyrics pajamas column facility finance tension extend space birthday rainbow swimming purple syndrome facility trial warn duration snapshot shadow hormone rhyme public spine counter easy hawk album"],
+            "8340611602fe91af634a5f4608377b5235fa2d757c51d720c0c7656249a3035f",
+        );
+        vector(
+            &[
+                "western apart academic always artist resident briefing sugar woman oven coding club ajar merit pecan answer prisoner artist fraction amount desktop mild false necklace muscle photo wealthy alpha category unwrap spew losing making",
+                "western apart academic acid answer ancient auction flip image penalty oasis beaver multiple thunder problem switch alive heat inherit superior teaspoon explain blanket pencil numb lend punish endless aunt garlic humidity kidney observe",
+            ],
+            "8dc652d6d6cd370d8c963141f6d79ba440300f25c467302c1d966bff8f62300d",
+        );
+    }
+
+    #[test]
+    fn test_trezor_invalid_vectors() {
+        // invalid checksum
+        rejected(&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney"]);
+        // invalid padding
+        rejected(&["duckling enlarge academic academic email result length solution fridge kidney coal piece deal husband erode duke ajar music cargo fitness"]);
+        // one share of a 2-of-3
+        rejected(&["shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed"]);
+        // different identifiers
+        rejected(&[
+            "adequate smoking academic acid debut wine petition glen cluster slow rhyme slow simple epidemic rumor junk tracks treat olympic tolerate",
+            "adequate stay academic agency agency formal party ting frequent learn upstairs remember smear leaf damage anatomy ladle market hush corner",
+        ]);
+        // different iteration exponents
+        rejected(&[
+            "peasant leaves academic acid desert exact olympic math alive axle trial tackle drug deny decent smear dominant desert bucket remind",
+            "peasant leader academic agency cultural blessing percent network envelope medal junk primary human pumps jacket fragment payroll ticket evoke voice",
+        ]);
+        // different group counts
+        rejected(&[
+            "average senior academic leaf broken teacher expect surface hour capture obesity desire negative dynamic dominant pistol mineral mailman iris aide",
+            "average senior academic agency curious pants blimp spew clothes slice script dress wrap firm shaft regular slavery negative theater roster",
+        ]);
+        // group threshold greater than the group count
+        rejected(&[
+            "music husband acrobat acid artist finance center either graduate swimming object bike medical clothes station aspect spider maiden bulb welcome",
+            "music husband acrobat agency advance hunting bike corner density careful material civil evil tactics remind hawk discuss hobo voice rainbow",
+            "music husband beard academic black tricycle clock mayor estimate level photo episode exclude ecology papa source amazing salt verify divorce",
+        ]);
+        // duplicate member indices
+        rejected(&[
+            "device stay academic always dive coal antenna adult black exceed stadium herald advance soldier busy dryer daughter evaluate minister laser",
+            "device stay academic always dwarf afraid robin gravity crunch adjust soul branch walnut coastal dream costume scholar mortgage mountain pumps",
+        ]);
+        // different member thresholds
+        rejected(&[
+            "hour painting academic academic device formal evoke guitar random modern justice filter withdraw trouble identify mailman insect general cover oven",
+            "hour painting academic agency artist again daisy capital beaver fiber much enjoy suitable symbolic identify photo editor romp float echo",
+        ]);
+        // invalid digest
+        rejected(&[
+            "guilt walnut academic acid deliver remove equip listen vampire tactics nylon rhythm failure husband fatigue alive blind enemy teaspoon rebound",
+            "guilt walnut academic agency brave hamster hobo declare herd taste alpha slim criminal mild arcade formal romp branch pink ambition",
+        ]);
+        // too few groups, or a group with too few shares
+        rejected(&[GROUP_1]);
+        rejected(&GROUP_2);
+        rejected(&GROUP_3);
+        rejected(&[GROUP_1, GROUP_2[0], GROUP_2[1]]);
+        // too short, and a master secret of 15 bytes
+        rejected(&["junk necklace academic academic acne isolate join hesitate lunar roster dough calcium chemical ladybug amount mobile glasses verify cylinder"]);
+        rejected(&["fraction necklace academic academic award teammate mouse regular testify coding building member verdict purchase blind camera duration email prepare spirit quarter"]);
+    }
+
+    #[test]
+    fn test_reject_extra_groups_shares_and_group_index() {
+        // the reference implementation takes exactly the thresholds, no more
+        rejected(&[GROUP_1, GROUP_0, GROUP_3[0], GROUP_3[1]]);
+        rejected(&[GROUP_3[0], GROUP_3[1], GROUP_1, GROUP_2[0]]);
+        let secret = [7u8; 16];
+        let shares = split(&secret, "", 1, &[GroupSpec::new(2, 3)], 0, false).unwrap();
+        let shares = shares[0].iter().map(|share| share.as_str()).collect::<Vec<_>>();
+        assert!(combine(&shares, "").is_err());
+        assert_eq!(combine(&shares[1..], "").unwrap().as_slice(), secret);
+
+        // a share claiming group 2 of a single group backup, with a valid checksum
+        let mut share = Share::parse(shares[0]).unwrap();
+        share.group_index = 1;
+        let share = share.to_mnemonic().unwrap();
+        assert!(matches!(combine(&[share.as_str()], ""), Err(AccountError::InvalidSlip39(_))));
+    }
+
+    #[test]
+    fn test_split_and_combine_groups() {
+        let secret = hex::decode("989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92").unwrap();
+        let groups = [GroupSpec::new(1, 1), GroupSpec::new(2, 3), GroupSpec::new(3, 5)];
+        let shares = split(&secret, "TREZOR", 2, &groups, 0, true).unwrap();
+        assert_eq!(shares.iter().map(Vec::len).collect::<Vec<_>>(), [1, 3, 5]);
+        assert_eq!(shares[1][0].split_whitespace().count(), 33);
+
+        let recovered = |picked: &[&Zeroizing<String>]| {
+            let picked = picked.iter().map(|share| share.as_str()).collect::<Vec<_>>();
+            combine(&picked, "TREZOR")
+        };
+        assert_eq!(recovered(&[&shares[0][0], &shares[1][2], &shares[1][0]]).unwrap().as_slice(), secret);
+        assert_eq!(
+            recovered(&[&shares[2][4], &shares[1][1], &shares[2][0], &shares[2][2], &shares[1][2]])
+                .unwrap()
+                .as_slice(),
+            secret
+        );
+
+        // one complete group, the other incomplete
+        assert!(recovered(&[&shares[0][0], &shares[1][0]]).is_err());
+        // a wrong passphrase recovers a different secret, there is nothing to check it against
+        let wrong = combine(&[shares[0][0].as_str(), shares[1][0].as_str(), shares[1][1].as_str()], "")
+            .unwrap();
+        assert_ne!(wrong.as_slice(), secret);
+    }
+
+    #[test]
+    fn test_reject_mixed_and_invalid_parameters() {
+        let secret = [7u8; 16];
+        let a = split(&secret, "", 1, &[GroupSpec::new(2, 3)], 0, false).unwrap();
+        let b = split(&secret, "", 1, &[GroupSpec::new(2, 3)], 0, false).unwrap();
+        assert!(combine(&[a[0][0].as_str(), b[0][1].as_str()], "").is_err());
+        assert_eq!(combine(&[a[0][0].as_str(), a[0][1].as_str()], "").unwrap().as_slice(), secret);
+
+        assert!(split(&[0u8; 15], "", 1, &[GroupSpec::new(1, 1)], 0, false).is_err());
+        assert!(split(&secret, "", 2, &[GroupSpec::new(1, 1)], 0, false).is_err());
+        assert!(split(&secret, "", 1, &[GroupSpec::new(1, 2)], 0, false).is_err());
+        assert!(split(&secret, "", 1, &[GroupSpec::new(3, 2)], 0, false).is_err());
+        assert!(split(&secret, "pässword", 1, &[GroupSpec::new(1, 1)], 0, false).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! One seed and the many accounts below it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint, Xpriv, Xpub};
+use bitcoin::{Address, Network, PublicKey};
+
+use crate::errors::{AccountError, Result};
+use crate::secp;
+
+use super::account::{address_for, wipe_xpriv, Account};
+use super::address_type::AddressType;
+use super::discovery::{self, ActiveAccount, ActivitySource};
+use super::mnemonic;
+
+/// Holds the master key of a seed and hands out its accounts.
+///
+/// The seed is stretched once, and account and chain level keys are derived the first time
+/// they are needed and kept, so further [`Wallet::account`]s only derive the last two levels
+/// and further [`Wallet::addresses`] a single public one. The wallet also tracks the next
+/// unused address index of every chain, `0` for receiving and `1` for change addresses.
+pub struct Wallet {
+    network: Network,
+    root: Xpriv,
+    fingerprint: Fingerprint,
+    account_keys: HashMap<(AddressType, u32), (Xpriv, Xpub)>,
+    chain_xpubs: HashMap<(AddressType, u32, u32), Xpub>,
+    next_index: HashMap<(AddressType, u32, u32), u32>,
+}
+
+impl Wallet {
+    /// The wallet of a BIP-39 `mnemonic` with an optional `passphrase`.
+    pub fn from_mnemonic(network: Network, mnemonic: &str, passphrase: &str) -> Result<Wallet> {
+        let seed = mnemonic::to_seed(mnemonic, passphrase)?;
+        Wallet::from_seed(network, seed.as_ref())
+    }
+
+    /// The wallet of a BIP-32 `seed`.
+    pub fn from_seed(network: Network, seed: &[u8]) -> Result<Wallet> {
+        Ok(Wallet::from_root_xpriv(network, Account::root_xpriv(network, seed)?))
+    }
+
+    pub fn from_root_xpriv(network: Network, root: Xpriv) -> Wallet {
+        let fingerprint = root.fingerprint(secp::context());
+        Wallet {
+            network,
+            root,
+            fingerprint,
+            account_keys: HashMap::new(),
+            chain_xpubs: HashMap::new(),
+            next_index: HashMap::new(),
+        }
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// The master key fingerprint, as found in PSBT key origins and descriptors.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint
+    }
+
+    /// The `address_type` account number `account` at `[chain, index]`.
+    pub fn account(&mut self, address_type: AddressType, account: u32, path: [u32; 2]) -> Result<Account> {
+        let hd_path = address_type.account_path(account)?;
+        let network = self.network;
+        let (xpriv, xpub) = self.account_keys(address_type, account, &hd_path)?;
+        Account::from_account_keys(network, xpriv, *xpub, hd_path, address_type, Some(path))
+    }
+
+    /// The addresses at `indices` of `chain`, derived from the public chain key only. This is
+    /// the fast way to generate addresses in bulk, e.g. to watch them.
+    pub fn addresses(
+        &mut self,
+        address_type: AddressType,
+        account: u32,
+        chain: u32,
+        indices: Range<u32>,
+    ) -> Result<Vec<Address>> {
+        let chain_xpub = self.chain_xpub(address_type, account, chain)?;
+        let secp256k1 = secp::context();
+        indices
+            .map(|index| {
+                let child_number = ChildNumber::from_normal_idx(index)
+                    .map_err(|e| AccountError::InvalidHDPath(e.to_string()))?;
+                let child = chain_xpub
+                    .ckd_pub(secp256k1, child_number)
+                    .map_err(|e| AccountError::Other(e.to_string()))?;
+                Ok(address_for(secp256k1, &PublicKey::new(child.public_key), address_type, self.network))
+            })
+            .collect()
+    }
+
+    /// The cached account keys, by reference so no copies of the xpriv are left to wipe.
+    fn account_keys(
+        &mut self,
+        address_type: AddressType,
+        account: u32,
+        hd_path: &DerivationPath,
+    ) -> Result<&(Xpriv, Xpub)> {
+        let key = (address_type, account);
+        if !self.account_keys.contains_key(&key) {
+            let secp256k1 = secp::context();
+            let mut xpriv = self
+                .root
+                .derive_priv(secp256k1, hd_path)
+                .map_err(|e| AccountError::Other(e.to_string()))?;
+            let xpub = Xpub::from_priv(secp256k1, &xpriv);
+            self.account_keys.insert(key, (xpriv, xpub));
+            wipe_xpriv(&mut xpriv);
+        }
+        Ok(&self.account_keys[&key])
+    }
+
+    fn chain_xpub(&mut self, address_type: AddressType, account: u32, chain: u32) -> Result<Xpub> {
+        if let Some(xpub) = self.chain_xpubs.get(&(address_type, account, chain)) {
+            return Ok(*xpub);
+        }
+        let hd_path = address_type.account_path(account)?;
+        let (_, account_xpub) = *self.account_keys(address_type, account, &hd_path)?;
+        let child_number =
+            ChildNumber::from_normal_idx(chain).map_err(|e| AccountError::InvalidHDPath(e.to_string()))?;
+        let xpub = account_xpub
+            .ckd_pub(secp::context(), child_number)
+            .map_err(|e| AccountError::Other(e.to_string()))?;
+        self.chain_xpubs.insert((address_type, account, chain), xpub);
+        Ok(xpub)
+    }
+
+    /// The first index of `chain` not marked as used.
+    pub fn next_index(&self, address_type: AddressType, account: u32, chain: u32) -> u32 {
+        self.next_index.get(&(address_type, account, chain)).copied().unwrap_or(0)
+    }
+
+    /// The account at [`Wallet::next_index`] of `chain`. It stays the same until it is marked
+    /// as used, so handing it out twice gives the same address.
+    pub fn next_unused(&mut self, address_type: AddressType, account: u32, chain: u32) -> Result<Account> {
+        let index = self.next_index(address_type, account, chain);
+        self.account(address_type, account, [chain, index])
+    }
+
+    /// Records that the address at `[chain, index]` received funds.
+    pub fn mark_used(&mut self, address_type: AddressType, account: u32, path: [u32; 2]) {
+        let [chain, index] = path;
+        let next = self.next_index.entry((address_type, account, chain)).or_insert(0);
+        *next = (*next).max(index.saturating_add(1));
+    }
+
+    /// Runs [`discovery::discover`] on the wallet and marks every used address it found.
+    pub fn discover<S: ActivitySource + ?Sized>(
+        &mut self,
+        source: &mut S,
+        gap_limit: u32,
+    ) -> Result<Vec<ActiveAccount>> {
+        let active = discovery::discover(self.network, &self.root, source, gap_limit)?;
+        for found in &active {
+            for (chain, last_used) in (0..).zip(found.last_used) {
+                if let Some(index) = last_used {
+                    self.mark_used(found.account.address_type, found.index, [chain, index]);
+                }
+            }
+        }
+        Ok(active)
+    }
+}
+
+impl Drop for Wallet {
+    fn drop(&mut self) {
+        let account_xprivs = self.account_keys.values_mut().map(|(xpriv, _)| xpriv);
+        for xpriv in std::iter::once(&mut self.root).chain(account_xprivs) {
+            wipe_xpriv(xpriv);
+        }
+    }
+}
+
+impl fmt::Debug for Wallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wallet")
+            .field("network", &self.network)
+            .field("root", &"<redacted>")
+            .field("fingerprint", &self.fingerprint)
+            .field("next_index", &self.next_index)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use bitcoin::Address;
+
+    use super::*;
+
+    const MNEMONIC: &str = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    #[test]
+    fn test_fingerprint() {
+        // BIP-32 test vector 1
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let wallet = Wallet::from_seed(Network::Bitcoin, &seed).unwrap();
+        assert_eq!(wallet.fingerprint().to_string(), "3442193e");
+        assert!(!format!("{:?}", wallet).contains("xprv"));
+    }
+
+    #[test]
+    fn test_accounts_match_from_mnemonic() {
+        let mut wallet = Wallet::from_mnemonic(Network::Bitcoin, MNEMONIC, "").unwrap();
+        for address_type in AddressType::ALL {
+            for path in [[0, 0], [1, 3]] {
+                let account = wallet.account(address_type, 0, path).unwrap();
+                let expected = Account::from_mnemonic(Network::Bitcoin, MNEMONIC, address_type, Some(path)).unwrap();
+                assert_eq!(account.address, expected.address);
+                assert_eq!(account.expose_wif_private_key(), expected.expose_wif_private_key());
+                assert_eq!(account.hd_path, expected.hd_path);
+            }
+        }
+
+        let second = wallet.account(AddressType::P2WPKH, 1, [0, 0]).unwrap();
+        assert_eq!(second.hd_path.as_ref().unwrap().to_string(), "84'/0'/1'");
+    }
+
+    #[test]
+    fn test_addresses() {
+        let mut wallet = Wallet::from_mnemonic(Network::Testnet, MNEMONIC, "").unwrap();
+        for address_type in AddressType::ALL {
+            let addresses = wallet.addresses(address_type, 2, 1, 3..6).unwrap();
+            assert_eq!(addresses.len(), 3);
+            for (index, address) in (3..).zip(&addresses) {
+                assert_eq!(*address, wallet.account(address_type, 2, [1, index]).unwrap().address);
+            }
+        }
+        assert!(wallet.addresses(AddressType::P2TR, 0, 0, (1 << 31)..(1 << 31) + 1).is_err());
+    }
+
+    #[test]
+    fn test_next_unused() {
+        let mut wallet = Wallet::from_mnemonic(Network::Bitcoin, MNEMONIC, "").unwrap();
+        let first = wallet.next_unused(AddressType::P2TR, 0, 0).unwrap();
+        assert_eq!(first.address, wallet.next_unused(AddressType::P2TR, 0, 0).unwrap().address);
+
+        wallet.mark_used(AddressType::P2TR, 0, [0, 0]);
+        assert_eq!(wallet.next_index(AddressType::P2TR, 0, 0), 1);
+        wallet.mark_used(AddressType::P2TR, 0, [0, 5]);
+        wallet.mark_used(AddressType::P2TR, 0, [0, 2]);
+        assert_eq!(wallet.next_index(AddressType::P2TR, 0, 0), 6);
+        assert_eq!(
+            wallet.next_unused(AddressType::P2TR, 0, 0).unwrap().address,
+            wallet.account(AddressType::P2TR, 0, [0, 6]).unwrap().address
+        );
+
+        // chains, accounts and types are tracked separately
+        assert_eq!(wallet.next_index(AddressType::P2TR, 0, 1), 0);
+        assert_eq!(wallet.next_index(AddressType::P2TR, 1, 0), 0);
+        assert_eq!(wallet.next_index(AddressType::P2WPKH, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_discover_marks_used() {
+        let mut wallet = Wallet::from_mnemonic(Network::Bitcoin, MNEMONIC, "").unwrap();
+        let used: HashSet<Address> = [
+            wallet.account(AddressType::P2WPKH, 0, [0, 2]).unwrap().address.clone(),
+            wallet.account(AddressType::P2WPKH, 0, [1, 0]).unwrap().address.clone(),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut source = |address: &Address| Ok(used.contains(address));
+        let active = wallet.discover(&mut source, 5).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(wallet.next_index(AddressType::P2WPKH, 0, 0), 3);
+        assert_eq!(wallet.next_index(AddressType::P2WPKH, 0, 1), 1);
+    }
+}
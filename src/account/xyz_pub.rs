@@ -1,10 +1,16 @@
+use core::fmt;
 use core::str::FromStr;
 
 use bitcoin::base58;
+use bitcoin::bip32::{ChainCode, ChildNumber, Fingerprint};
+use bitcoin::secp256k1::{PublicKey, SecretKey};
+use bitcoin::NetworkKind;
 
 use crate::errors::AccountError;
 use crate::errors::Result;
 
+use super::address_type::AddressType;
+
 /// Version bytes xpub: bitcoin mainnet public key P2PKH or P2SH
 pub const VERSION_XPUB: [u8; 4] = [0x04, 0x88, 0xB2, 0x1E];
 
@@ -65,7 +71,21 @@ pub const VERSION_VPUB_MULTISIG: [u8; 4] = [0x02, 0x57, 0x54, 0x83];
 /// Version bytes Vprv: bitcoin testnet private key for multi-signature P2WSH
 pub const VERSION_VPRV_MULTISIG: [u8; 4] = [0x02, 0x57, 0x50, 0x48];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Output script an extended key version is meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptType {
+    /// P2PKH or P2SH, also used for P2TR since taproot has no version of its own
+    P2pkhOrP2sh,
+    /// P2WPKH nested in P2SH
+    P2shP2wpkh,
+    P2wpkh,
+    /// multi-signature P2WSH nested in P2SH
+    P2shP2wsh,
+    /// multi-signature P2WSH
+    P2wsh,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 /// Enum for version bytes.
 pub enum Version {
     /// Version bytes xpub: bitcoin mainnet public key P2PKH or P2SH
@@ -130,6 +150,116 @@ pub enum Version {
 }
 
 impl Version {
+    pub const ALL: [Version; 20] = [
+        Version::Xpub,
+        Version::Ypub,
+        Version::Zpub,
+        Version::Tpub,
+        Version::Upub,
+        Version::Vpub,
+        Version::Xprv,
+        Version::Yprv,
+        Version::Zprv,
+        Version::Tprv,
+        Version::Uprv,
+        Version::Vprv,
+        Version::YpubMultisig,
+        Version::ZpubMultisig,
+        Version::UpubMultisig,
+        Version::VpubMultisig,
+        Version::YprvMultisig,
+        Version::ZprvMultisig,
+        Version::UprvMultisig,
+        Version::VprvMultisig,
+    ];
+
+    /// The version with these version bytes.
+    pub fn from_bytes(bytes: [u8; 4]) -> Option<Version> {
+        Version::ALL.into_iter().find(|version| version.bytes() == bytes)
+    }
+
+    /// True for versions of extended private keys.
+    pub fn is_private(&self) -> bool {
+        matches!(
+            self,
+            Version::Xprv
+                | Version::Yprv
+                | Version::Zprv
+                | Version::Tprv
+                | Version::Uprv
+                | Version::Vprv
+                | Version::YprvMultisig
+                | Version::ZprvMultisig
+                | Version::UprvMultisig
+                | Version::VprvMultisig
+        )
+    }
+
+    /// True for the capitalized multi-signature versions.
+    pub fn is_multisig(&self) -> bool {
+        matches!(self.script_type(), ScriptType::P2shP2wsh | ScriptType::P2wsh)
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        match self {
+            Version::Xpub | Version::Tpub | Version::Xprv | Version::Tprv => ScriptType::P2pkhOrP2sh,
+            Version::Ypub | Version::Upub | Version::Yprv | Version::Uprv => ScriptType::P2shP2wpkh,
+            Version::Zpub | Version::Vpub | Version::Zprv | Version::Vprv => ScriptType::P2wpkh,
+            Version::YpubMultisig
+            | Version::UpubMultisig
+            | Version::YprvMultisig
+            | Version::UprvMultisig => ScriptType::P2shP2wsh,
+            Version::ZpubMultisig
+            | Version::VpubMultisig
+            | Version::ZprvMultisig
+            | Version::VprvMultisig => ScriptType::P2wsh,
+        }
+    }
+
+    /// The single-key address type of keys with this version, `None` for multi-signature
+    /// versions. `xpub` keys are reported as P2PKH though P2TR wallets use them too.
+    pub fn address_type(&self) -> Option<AddressType> {
+        match self.script_type() {
+            ScriptType::P2pkhOrP2sh => Some(AddressType::P2PKH),
+            ScriptType::P2shP2wpkh => Some(AddressType::P2SH_P2WPKH),
+            ScriptType::P2wpkh => Some(AddressType::P2WPKH),
+            ScriptType::P2shP2wsh | ScriptType::P2wsh => None,
+        }
+    }
+
+    /// The version wallets expect for `address_type` accounts on `network`.
+    pub fn for_address_type(address_type: AddressType, network: NetworkKind, private: bool) -> Version {
+        let script_type = match address_type {
+            AddressType::P2PKH | AddressType::P2TR => ScriptType::P2pkhOrP2sh,
+            AddressType::P2SH_P2WPKH => ScriptType::P2shP2wpkh,
+            AddressType::P2WPKH => ScriptType::P2wpkh,
+        };
+        Version::ALL
+            .into_iter()
+            .find(|version| {
+                version.script_type() == script_type
+                    && version.network() == network
+                    && version.is_private() == private
+            })
+            .expect("every single-key script type has a version")
+    }
+
+    pub fn network(&self) -> NetworkKind {
+        match self {
+            Version::Xpub
+            | Version::Ypub
+            | Version::Zpub
+            | Version::Xprv
+            | Version::Yprv
+            | Version::Zprv
+            | Version::YpubMultisig
+            | Version::ZpubMultisig
+            | Version::YprvMultisig
+            | Version::ZprvMultisig => NetworkKind::Main,
+            _ => NetworkKind::Test,
+        }
+    }
+
     /// Returns the version bytes.
     pub fn bytes(&self) -> [u8; 4] {
         match self {
@@ -157,6 +287,34 @@ impl Version {
     }
 }
 
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match self {
+            Version::Xpub => "xpub",
+            Version::Ypub => "ypub",
+            Version::Zpub => "zpub",
+            Version::Tpub => "tpub",
+            Version::Upub => "upub",
+            Version::Vpub => "vpub",
+            Version::Xprv => "xprv",
+            Version::Yprv => "yprv",
+            Version::Zprv => "zprv",
+            Version::Tprv => "tprv",
+            Version::Uprv => "uprv",
+            Version::Vprv => "vprv",
+            Version::YpubMultisig => "Ypub",
+            Version::ZpubMultisig => "Zpub",
+            Version::UpubMultisig => "Upub",
+            Version::VpubMultisig => "Vpub",
+            Version::YprvMultisig => "Yprv",
+            Version::ZprvMultisig => "Zprv",
+            Version::UprvMultisig => "Uprv",
+            Version::VprvMultisig => "Vprv",
+        };
+        f.write_str(prefix)
+    }
+}
+
 impl FromStr for Version {
     type Err = AccountError;
     fn from_str(s: &str) -> Result<Self> {
@@ -186,6 +344,139 @@ impl FromStr for Version {
     }
 }
 
+/// Length of a serialized extended key.
+const EXTENDED_KEY_LENGTH: usize = 78;
+
+/// Key material of an extended key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyData {
+    Public(PublicKey),
+    Private(SecretKey),
+}
+
+/// A decoded and validated BIP-32 extended key in any SLIP-132 version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedKey {
+    pub version: Version,
+    pub depth: u8,
+    pub parent_fingerprint: Fingerprint,
+    pub child_number: ChildNumber,
+    pub chain_code: ChainCode,
+    pub key: KeyData,
+}
+
+impl ExtendedKey {
+    /// Decodes a serialized extended key, checking the version is known, the key is valid for
+    /// the version's kind and a master key has no parent.
+    pub fn decode(bytes: &[u8]) -> Result<ExtendedKey> {
+        if bytes.len() != EXTENDED_KEY_LENGTH {
+            return Err(invalid_key(format!(
+                "expected {} bytes, got {}",
+                EXTENDED_KEY_LENGTH,
+                bytes.len()
+            )));
+        }
+
+        let version = Version::from_bytes(bytes[0..4].try_into().expect("4 bytes"))
+            .ok_or_else(|| invalid_key(format!("unknown version {}", hex::encode(&bytes[0..4]))))?;
+        let depth = bytes[4];
+        let parent_fingerprint = Fingerprint::from(<[u8; 4]>::try_from(&bytes[5..9]).expect("4 bytes"));
+        let child_number =
+            ChildNumber::from(u32::from_be_bytes(bytes[9..13].try_into().expect("4 bytes")));
+        let chain_code = ChainCode::from(<[u8; 32]>::try_from(&bytes[13..45]).expect("32 bytes"));
+
+        let key = if version.is_private() {
+            if bytes[45] != 0 {
+                return Err(invalid_key("private key data must start with a zero byte"));
+            }
+            KeyData::Private(SecretKey::from_slice(&bytes[46..]).map_err(|e| invalid_key(e.to_string()))?)
+        } else {
+            KeyData::Public(PublicKey::from_slice(&bytes[45..]).map_err(|e| invalid_key(e.to_string()))?)
+        };
+
+        if depth == 0 && (parent_fingerprint != Fingerprint::default() || u32::from(child_number) != 0) {
+            return Err(invalid_key("master key with a parent fingerprint or child number"));
+        }
+
+        Ok(ExtendedKey {
+            version,
+            depth,
+            parent_fingerprint,
+            child_number,
+            chain_code,
+            key,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; EXTENDED_KEY_LENGTH] {
+        let mut bytes = [0u8; EXTENDED_KEY_LENGTH];
+        bytes[0..4].copy_from_slice(&self.version.bytes());
+        bytes[4] = self.depth;
+        bytes[5..9].copy_from_slice(self.parent_fingerprint.as_bytes());
+        bytes[9..13].copy_from_slice(&u32::from(self.child_number).to_be_bytes());
+        bytes[13..45].copy_from_slice(self.chain_code.as_bytes());
+        match &self.key {
+            KeyData::Public(public_key) => bytes[45..].copy_from_slice(&public_key.serialize()),
+            KeyData::Private(secret_key) => bytes[46..].copy_from_slice(&secret_key.secret_bytes()),
+        }
+        bytes
+    }
+
+    /// True if the key is for `network`.
+    pub fn is_for(&self, network: NetworkKind) -> bool {
+        self.version.network() == network
+    }
+
+    /// The same key with `target` version bytes. Public keys cannot be turned into private keys
+    /// and private keys are not silently made public. The network must stay the same, see
+    /// [`ExtendedKey::convert_network`] to move a key to another network.
+    pub fn convert(&self, target: &Version) -> Result<ExtendedKey> {
+        if target.network() != self.version.network() {
+            return Err(invalid_key(format!(
+                "cannot convert a {} key to {}, use convert_network",
+                if self.version.network().is_mainnet() { "mainnet" } else { "testnet" },
+                if target.network().is_mainnet() { "mainnet" } else { "testnet" }
+            )));
+        }
+        self.convert_network(target)
+    }
+
+    /// Like [`ExtendedKey::convert`] but also allows `target` to be for another network, e.g. to
+    /// use a mainnet xpub as a tpub on testnet.
+    pub fn convert_network(&self, target: &Version) -> Result<ExtendedKey> {
+        if target.is_private() != self.version.is_private() {
+            return Err(invalid_key(format!(
+                "cannot convert a {} key to a {} key",
+                if self.version.is_private() { "private" } else { "public" },
+                if target.is_private() { "private" } else { "public" }
+            )));
+        }
+        Ok(ExtendedKey {
+            version: *target,
+            ..self.clone()
+        })
+    }
+}
+
+impl FromStr for ExtendedKey {
+    type Err = AccountError;
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = base58::decode_check(s)
+            .map_err(|e| invalid_key(format!("invalid base58 string: {}", e)))?;
+        ExtendedKey::decode(&bytes)
+    }
+}
+
+impl fmt::Display for ExtendedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&base58::encode_check(&self.encode()))
+    }
+}
+
+fn invalid_key(message: impl Into<String>) -> AccountError {
+    AccountError::InvalidExtendedKey(message.into())
+}
+
 /// Replaces the first 4 bytes of a byte slice with the target's version and returns a new byte vec.
 /// Does not check if extended public/private key is valid and only replaces the version bytes.
 pub fn replace_version_bytes<B: AsRef<[u8]>>(bytes: B, target: &Version) -> Result<Vec<u8>> {
@@ -198,13 +489,12 @@ pub fn replace_version_bytes<B: AsRef<[u8]>>(bytes: B, target: &Version) -> Resu
     Ok(vec)
 }
 
-/// Replaces the first 4 bytes of a base58 string with the target's version and returns the new string.
-/// Also checks if the input is a correct address.
+/// Replaces the version of a base58 extended key with the target's version and returns the new
+/// string. The input must be a valid extended key and keep its public or private kind.
 pub fn convert_version<S: AsRef<str>>(str: S, target: &Version) -> Result<String> {
-    let bytes = base58::decode_check(str.as_ref()).map_err(|e| AccountError::Other("invalid base58 string: ".to_string() + &e.to_string()))?;
-    let replaced = replace_version_bytes(bytes, target)?;
+    let key = ExtendedKey::from_str(str.as_ref())?;
 
-    Ok(base58::encode_check(&replaced))
+    Ok(key.convert(target)?.to_string())
 }
 
 #[cfg(test)]
@@ -273,8 +563,129 @@ mod tests {
         let xpub = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
         let expected_tpub = "tpubDCBWBScQPGv4Xk3JSbhw6wYYpayMjb2eAYyArpbSqQTbLDpphHGAetB6VQgVeftLML8vDSUEWcC2xDi3qJJ3YCDChJDvqVzpgoYSuT52MhJ";
 
-        let result = convert_version(xpub, &Version::Tpub).unwrap();
+        let key = ExtendedKey::from_str(xpub).unwrap();
+        let result = key.convert_network(&Version::Tpub).unwrap().to_string();
 
         assert_eq!(result, expected_tpub);
+        // a plain conversion keeps the network
+        assert!(matches!(key.convert(&Version::Tpub), Err(AccountError::InvalidExtendedKey(_))));
+        assert!(matches!(convert_version(xpub, &Version::Vpub), Err(AccountError::InvalidExtendedKey(_))));
+        assert!(matches!(
+            convert_version(expected_tpub, &Version::Zpub),
+            Err(AccountError::InvalidExtendedKey(_))
+        ));
+    }
+
+    #[test]
+    fn parse_extended_key() {
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+        let key = ExtendedKey::from_str(zpub).unwrap();
+
+        assert_eq!(key.version, Version::Zpub);
+        assert_eq!(key.depth, 3);
+        assert_eq!(key.child_number, ChildNumber::from_hardened_idx(0).unwrap());
+        assert!(matches!(key.key, KeyData::Public(_)));
+        assert!(key.is_for(NetworkKind::Main));
+        assert_eq!(key.to_string(), zpub);
+    }
+
+    #[test]
+    fn parse_private_extended_key() {
+        let xprv = "xprv9y6ctoiHQPqekbRQi8EYzw1DxD47E6S5iERL4kREhuTWXfTzkd12CcfVeRen942QQ7cnszCYgattcBdNSR2r4WCzj4hcfCo5cyjr8AFoqEa";
+
+        let key = ExtendedKey::from_str(xprv).unwrap();
+
+        assert!(matches!(key.key, KeyData::Private(_)));
+        assert_eq!(key.to_string(), xprv);
+        let zprv = convert_version(xprv, &Version::Zprv).unwrap();
+        assert!(zprv.starts_with("zprv"));
+        assert_eq!(convert_version(zprv, &Version::Xprv).unwrap(), xprv);
+    }
+
+    #[test]
+    fn err_when_changing_public_or_private() {
+        let xpub = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+        let xprv = "xprv9y6ctoiHQPqekbRQi8EYzw1DxD47E6S5iERL4kREhuTWXfTzkd12CcfVeRen942QQ7cnszCYgattcBdNSR2r4WCzj4hcfCo5cyjr8AFoqEa";
+
+        assert!(matches!(convert_version(xpub, &Version::Zprv), Err(AccountError::InvalidExtendedKey(_))));
+        assert!(matches!(convert_version(xprv, &Version::Zpub), Err(AccountError::InvalidExtendedKey(_))));
+    }
+
+    #[test]
+    fn err_when_payload_invalid() {
+        let xpub = ExtendedKey::from_str(
+            "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj",
+        )
+        .unwrap()
+        .encode();
+
+        // truncated
+        let result = convert_version(base58::encode_check(&xpub[..77]), &Version::Zpub);
+        assert!(matches!(result, Err(AccountError::InvalidExtendedKey(_))));
+
+        // unknown version
+        let mut bytes = xpub;
+        bytes[0..4].copy_from_slice(&[1, 2, 3, 4]);
+        assert!(ExtendedKey::decode(&bytes).is_err());
+
+        // not a point on the curve
+        let mut bytes = xpub;
+        bytes[45] = 0x05;
+        assert!(ExtendedKey::decode(&bytes).is_err());
+
+        // private key data under a public version
+        let mut bytes = xpub;
+        bytes[45] = 0x00;
+        assert!(ExtendedKey::decode(&bytes).is_err());
+
+        // master key with a parent
+        let mut bytes = xpub;
+        bytes[4] = 0;
+        assert!(ExtendedKey::decode(&bytes).is_err());
+
+        // not base58, and a broken checksum
+        assert!(matches!(ExtendedKey::from_str("xpub0OIl"), Err(AccountError::InvalidExtendedKey(_))));
+        let mut encoded = base58::encode_check(&xpub);
+        encoded.pop();
+        encoded.push('1');
+        assert!(matches!(ExtendedKey::from_str(&encoded), Err(AccountError::InvalidExtendedKey(_))));
+    }
+
+    #[test]
+    fn version_metadata_round_trips() {
+        for version in Version::ALL {
+            assert_eq!(Version::from_bytes(version.bytes()), Some(version));
+            assert_eq!(Version::from_str(&version.to_string()).unwrap(), version);
+            if let Some(address_type) = version.address_type() {
+                assert_eq!(
+                    Version::for_address_type(address_type, version.network(), version.is_private()),
+                    version
+                );
+            }
+        }
+        assert_eq!(Version::from_bytes([0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn version_metadata() {
+        assert_eq!(Version::Vprv.network(), NetworkKind::Test);
+        assert!(Version::Vprv.is_private());
+        assert_eq!(Version::Vprv.address_type(), Some(AddressType::P2WPKH));
+        assert_eq!(Version::YpubMultisig.script_type(), ScriptType::P2shP2wsh);
+        assert!(Version::YpubMultisig.is_multisig());
+        assert_eq!(Version::YpubMultisig.address_type(), None);
+        assert_eq!(Version::for_address_type(AddressType::P2TR, NetworkKind::Main, false), Version::Xpub);
+        assert_eq!(Version::for_address_type(AddressType::P2SH_P2WPKH, NetworkKind::Test, true), Version::Uprv);
+    }
+
+    #[test]
+    fn detect_wallet_type_of_pasted_key() {
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+        let key = ExtendedKey::from_str(zpub).unwrap();
+
+        assert_eq!(key.version.address_type(), Some(AddressType::P2WPKH));
+        assert_eq!(key.version.to_string(), "zpub");
     }
 }
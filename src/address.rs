@@ -0,0 +1,241 @@
+//! Parsing and classification of any Bitcoin address string.
+
+use std::str::FromStr;
+
+use bech32::primitives::decode::{CharError, SegwitHrpstring, SegwitHrpstringError, UncheckedHrpstringError};
+use bitcoin::{base58, Address, Network, Script, ScriptBuf, WitnessVersion};
+
+use crate::account::address_type::AddressType;
+use crate::errors::{AccountError, Result};
+
+/// human readable parts of segwit addresses on mainnet, testnet/signet and regtest
+const SEGWIT_HRPS: [&str; 3] = ["bc1", "tb1", "bcrt1"];
+
+/// What an address pays to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressKind {
+    /// one of the single key types an [`crate::account::account::Account`] produces. P2SH
+    /// addresses never land here since the redeem script is not part of the address.
+    Standard(AddressType),
+    /// pay to script hash, possibly P2SH-P2WPKH
+    P2SH,
+    /// pay to witness script hash
+    P2WSH,
+    /// a witness version or program length with no defined meaning yet
+    UnknownWitness,
+}
+
+/// Everything an address string encodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressInfo {
+    pub address: Address,
+    /// `Testnet` for every testnet and signet address, which share their encoding
+    pub network: Network,
+    pub kind: AddressKind,
+    pub witness_version: Option<WitnessVersion>,
+    pub witness_program: Option<Vec<u8>>,
+    pub script_pubkey: ScriptBuf,
+}
+
+/// What a scriptPubKey pays to, the inverse of [`AddressType`] for utxo handling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptKind {
+    /// one of the single key types an [`crate::account::account::Account`] produces
+    Standard(AddressType),
+    /// pay to script hash with an unknown or non P2WPKH redeem script
+    P2SH,
+    P2WSH,
+    UnknownWitness,
+    OpReturn,
+    NonStandard,
+}
+
+/// Classifies `script_pubkey`. A P2SH script is reported as P2SH-P2WPKH when `redeem_script`
+/// is a P2WPKH script hashing to it.
+pub fn classify_script(script_pubkey: &Script, redeem_script: Option<&Script>) -> ScriptKind {
+    if script_pubkey.is_p2pkh() {
+        ScriptKind::Standard(AddressType::P2PKH)
+    } else if script_pubkey.is_p2sh() {
+        match redeem_script {
+            Some(redeem) if redeem.is_p2wpkh() && redeem.to_p2sh() == *script_pubkey => {
+                ScriptKind::Standard(AddressType::P2SH_P2WPKH)
+            }
+            _ => ScriptKind::P2SH,
+        }
+    } else if script_pubkey.is_p2wpkh() {
+        ScriptKind::Standard(AddressType::P2WPKH)
+    } else if script_pubkey.is_p2wsh() {
+        ScriptKind::P2WSH
+    } else if script_pubkey.is_p2tr() {
+        ScriptKind::Standard(AddressType::P2TR)
+    } else if script_pubkey.is_witness_program() {
+        ScriptKind::UnknownWitness
+    } else if script_pubkey.is_op_return() {
+        ScriptKind::OpReturn
+    } else {
+        ScriptKind::NonStandard
+    }
+}
+
+/// Parses a base58, bech32 or bech32m address of any network and classifies it.
+pub fn parse_address(s: &str) -> Result<AddressInfo> {
+    let lowercase = s.to_ascii_lowercase();
+    if SEGWIT_HRPS.iter().any(|hrp| lowercase.starts_with(hrp)) {
+        check_segwit(s)?;
+    } else {
+        check_base58(s)?;
+    }
+
+    let unchecked = Address::from_str(s).map_err(|e| AccountError::InvalidAddress(format!("{}: {}", s, e)))?;
+    let network = [Network::Bitcoin, Network::Testnet, Network::Regtest]
+        .into_iter()
+        .find(|network| unchecked.is_valid_for_network(*network))
+        .ok_or_else(|| AccountError::InvalidAddress(format!("{}: unknown network", s)))?;
+    let address = unchecked.assume_checked();
+
+    let kind = match address.address_type() {
+        Some(bitcoin::AddressType::P2pkh) => AddressKind::Standard(AddressType::P2PKH),
+        Some(bitcoin::AddressType::P2wpkh) => AddressKind::Standard(AddressType::P2WPKH),
+        Some(bitcoin::AddressType::P2tr) => AddressKind::Standard(AddressType::P2TR),
+        Some(bitcoin::AddressType::P2sh) => AddressKind::P2SH,
+        Some(bitcoin::AddressType::P2wsh) => AddressKind::P2WSH,
+        _ => AddressKind::UnknownWitness,
+    };
+    let program = address.witness_program();
+
+    Ok(AddressInfo {
+        network,
+        kind,
+        witness_version: program.map(|program| program.version()),
+        witness_program: program.map(|program| program.program().as_bytes().to_vec()),
+        script_pubkey: address.script_pubkey(),
+        address,
+    })
+}
+
+/// Decodes `s` as a segwit address to report case and checksum errors precisely, including
+/// bech32 used where bech32m is required and vice versa.
+fn check_segwit(s: &str) -> Result<()> {
+    match SegwitHrpstring::new(s) {
+        Ok(_) => Ok(()),
+        Err(SegwitHrpstringError::Unchecked(UncheckedHrpstringError::Char(CharError::MixedCase))) => {
+            Err(AccountError::InvalidAddressCase(s.to_string()))
+        }
+        Err(SegwitHrpstringError::Checksum(_)) => Err(AccountError::InvalidAddressChecksum(s.to_string())),
+        Err(e) => Err(AccountError::InvalidAddress(format!("{}: {}", s, e))),
+    }
+}
+
+fn check_base58(s: &str) -> Result<()> {
+    match base58::decode_check(s) {
+        Ok(_) => Ok(()),
+        Err(base58::Error::IncorrectChecksum(_)) => Err(AccountError::InvalidAddressChecksum(s.to_string())),
+        Err(e) => Err(AccountError::InvalidAddress(format!("{}: {}", s, e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_every_kind() {
+        let info = parse_address("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap();
+        assert_eq!(info.network, Network::Bitcoin);
+        assert_eq!(info.kind, AddressKind::Standard(AddressType::P2PKH));
+        assert_eq!(info.witness_version, None);
+        assert_eq!(
+            info.script_pubkey.to_hex_string(),
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"
+        );
+
+        let info = parse_address("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").unwrap();
+        assert_eq!(info.kind, AddressKind::P2SH);
+
+        let info = parse_address("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        assert_eq!(info.kind, AddressKind::Standard(AddressType::P2WPKH));
+        assert_eq!(info.witness_version, Some(WitnessVersion::V0));
+        assert_eq!(hex::encode(info.witness_program.unwrap()), "751e76e8199196d454941c45d1b3a323f1433bd6");
+        assert_eq!(info.script_pubkey.to_hex_string(), "0014751e76e8199196d454941c45d1b3a323f1433bd6");
+
+        let info = parse_address("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7").unwrap();
+        assert_eq!(info.network, Network::Testnet);
+        assert_eq!(info.kind, AddressKind::P2WSH);
+
+        let info = parse_address("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0").unwrap();
+        assert_eq!(info.kind, AddressKind::Standard(AddressType::P2TR));
+        assert_eq!(info.witness_version, Some(WitnessVersion::V1));
+
+        // upper case is valid as long as it is not mixed
+        let info = parse_address("BC1SW50QGDZ25J").unwrap();
+        assert_eq!(info.kind, AddressKind::UnknownWitness);
+        assert_eq!(info.witness_version, Some(WitnessVersion::V16));
+        assert_eq!(info.witness_program, Some(vec![0x75, 0x1e]));
+    }
+
+    #[test]
+    fn test_classify_script() {
+        use bitcoin::CompressedPublicKey;
+
+        use crate::account::account::Account;
+
+        let mnemonic = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        for address_type in [
+            AddressType::P2PKH,
+            AddressType::P2WPKH,
+            AddressType::P2TR,
+        ] {
+            let account = Account::from_mnemonic(Network::Bitcoin, mnemonic, address_type, None).unwrap();
+            assert_eq!(
+                classify_script(&account.address.script_pubkey(), None),
+                ScriptKind::Standard(address_type)
+            );
+        }
+
+        let nested =
+            Account::from_mnemonic(Network::Bitcoin, mnemonic, AddressType::P2SH_P2WPKH, None).unwrap();
+        let compressed = CompressedPublicKey(nested.public_key.inner);
+        let redeem_script = ScriptBuf::new_p2wpkh(&compressed.wpubkey_hash());
+        let script_pubkey = nested.address.script_pubkey();
+        assert_eq!(classify_script(&script_pubkey, None), ScriptKind::P2SH);
+        assert_eq!(
+            classify_script(&script_pubkey, Some(&redeem_script)),
+            ScriptKind::Standard(AddressType::P2SH_P2WPKH)
+        );
+        // a redeem script of another key does not count
+        let other = Account::from_mnemonic(Network::Bitcoin, mnemonic, AddressType::P2WPKH, Some([0, 1]))
+            .unwrap()
+            .address
+            .script_pubkey();
+        assert_eq!(classify_script(&script_pubkey, Some(&other)), ScriptKind::P2SH);
+
+        let p2wsh = parse_address("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7").unwrap();
+        assert_eq!(classify_script(&p2wsh.script_pubkey, None), ScriptKind::P2WSH);
+        let unknown = parse_address("BC1SW50QGDZ25J").unwrap();
+        assert_eq!(classify_script(&unknown.script_pubkey, None), ScriptKind::UnknownWitness);
+        assert_eq!(classify_script(&ScriptBuf::new_op_return([1u8, 2, 3]), None), ScriptKind::OpReturn);
+        assert_eq!(classify_script(&ScriptBuf::from_bytes(vec![0x51]), None), ScriptKind::NonStandard);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse_address("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"),
+            Err(AccountError::InvalidAddressChecksum(_))
+        ));
+        assert!(matches!(
+            parse_address("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"),
+            Err(AccountError::InvalidAddressChecksum(_))
+        ));
+        // taproot address with a bech32 instead of a bech32m checksum
+        assert!(matches!(
+            parse_address("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd"),
+            Err(AccountError::InvalidAddressChecksum(_))
+        ));
+        assert!(matches!(
+            parse_address("bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            Err(AccountError::InvalidAddressCase(_))
+        ));
+        assert!(matches!(parse_address("not an address"), Err(AccountError::InvalidAddress(_))));
+    }
+}
@@ -0,0 +1,260 @@
+//! BIP-322 generic signed messages, proving control of an address by signing a virtual
+//! transaction that spends from it.
+
+use bitcoin::absolute::LockTime;
+use bitcoin::base64::engine::general_purpose::STANDARD;
+use bitcoin::base64::Engine;
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::opcodes::all::OP_RETURN;
+use bitcoin::script::{Builder, PushBytesBuf};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Amount, CompressedPublicKey, OutPoint, Psbt, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+};
+
+use crate::account::account::Account;
+use crate::account::address_type::AddressType;
+use crate::address::parse_address;
+use crate::errors::{AccountError, Result};
+use crate::psbt::{finalize_psbt, p2sh_script_sig, sign_psbt, verify_spend};
+
+const TAG: &[u8] = b"BIP0322-signed-message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureFormat {
+    /// the witness of the signing transaction, for segwit addresses only
+    Simple,
+    /// the whole signing transaction, for every address type
+    Full,
+}
+
+/// The BIP-340 tagged hash of `message` committed to by the virtual transactions.
+pub fn message_hash(message: &[u8]) -> sha256::Hash {
+    let tag = sha256::Hash::hash(TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message);
+    sha256::Hash::from_engine(engine)
+}
+
+/// Signs `message` with the key of `account.address` as a base64 BIP-322 signature.
+///
+/// P2PKH addresses have no witness and need [`SignatureFormat::Full`]. P2SH-P2WPKH simple
+/// signatures leave out the script sig, verifiers rebuild it from the witness.
+pub fn sign(account: &Account, message: &str, format: SignatureFormat) -> Result<String> {
+    if format == SignatureFormat::Simple && account.address_type == AddressType::P2PKH {
+        return Err(AccountError::Signing("simple signatures need a segwit address".to_string()));
+    }
+
+    let to_spend = to_spend(&account.address.script_pubkey(), message.as_bytes());
+    let mut psbt = Psbt::from_unsigned_tx(to_sign(&to_spend)).map_err(|e| AccountError::Signing(e.to_string()))?;
+    psbt.inputs[0].witness_utxo = Some(to_spend.output[0].clone());
+    if account.address_type == AddressType::P2PKH {
+        psbt.inputs[0].non_witness_utxo = Some(to_spend);
+    }
+    sign_psbt(&mut psbt, account)?;
+    let signed = finalize_psbt(psbt)?;
+
+    let encoded = match format {
+        SignatureFormat::Simple => serialize(&signed.input[0].witness),
+        SignatureFormat::Full => serialize(&signed),
+    };
+    Ok(STANDARD.encode(encoded))
+}
+
+/// Checks that `signature`, simple or full, signs `message` for `address`. Any address parses
+/// but only the single key types of [`AddressType`] can be verified.
+pub fn verify(address: &str, message: &str, signature: &str) -> Result<()> {
+    let script_pubkey = parse_address(address)?.script_pubkey;
+    let decoded = STANDARD
+        .decode(signature)
+        .map_err(|e| AccountError::InvalidSignature(format!("not base64: {}", e)))?;
+
+    let to_spend = to_spend(&script_pubkey, message.as_bytes());
+    let mut expected = to_sign(&to_spend);
+    let signed = match deserialize::<Transaction>(&decoded) {
+        Ok(full) => {
+            // the version, lock time and sequence are up to the signer in the full format
+            let spends_message = full.input.len() == 1
+                && full.input[0].previous_output == expected.input[0].previous_output
+                && full.output == expected.output;
+            if !spends_message {
+                return Err(AccountError::InvalidSignature(
+                    "not a signing transaction for this message and address".to_string(),
+                ));
+            }
+            full
+        }
+        Err(_) => {
+            let witness: Witness = deserialize(&decoded).map_err(|_| {
+                AccountError::InvalidSignature("neither a simple nor a full signature".to_string())
+            })?;
+            if script_pubkey.is_p2sh() && witness.len() == 2 {
+                if let Ok(public_key) = CompressedPublicKey::from_slice(&witness[1]) {
+                    let redeem_script = ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash());
+                    expected.input[0].script_sig = p2sh_script_sig(&redeem_script)?;
+                }
+            }
+            expected.input[0].witness = witness;
+            expected
+        }
+    };
+
+    verify_spend(&signed, &to_spend.output)
+}
+
+/// The virtual transaction funding `script_pubkey` with a commitment to `message`.
+fn to_spend(script_pubkey: &Script, message: &[u8]) -> Transaction {
+    let message_hash = PushBytesBuf::from(message_hash(message).to_byte_array());
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: Hash::all_zeros(), vout: 0xffff_ffff },
+            script_sig: Builder::new().push_int(0).push_slice(message_hash).into_script(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut { value: Amount::ZERO, script_pubkey: script_pubkey.to_owned() }],
+    }
+}
+
+/// The unsigned virtual transaction spending `to_spend`.
+fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: to_spend.compute_txid(), vout: 0 },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Network;
+
+    use super::*;
+
+    // test vectors from BIP-322
+    const WIF: &str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+    const P2WPKH_ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+
+    #[test]
+    fn test_message_hash_and_virtual_transactions() {
+        assert_eq!(
+            message_hash(b"").to_string(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            message_hash(b"Hello World").to_string(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let script_pubkey = parse_address(P2WPKH_ADDRESS).unwrap().script_pubkey;
+        let spend = to_spend(&script_pubkey, b"");
+        assert_eq!(
+            spend.compute_txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            to_sign(&spend).compute_txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+
+        let spend = to_spend(&script_pubkey, b"Hello World");
+        assert_eq!(
+            spend.compute_txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+        assert_eq!(
+            to_sign(&spend).compute_txid().to_string(),
+            "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+        );
+    }
+
+    #[test]
+    fn test_vectors() {
+        let account = Account::from_wif(WIF, Network::Bitcoin, AddressType::P2WPKH).unwrap();
+        assert_eq!(account.address.to_string(), P2WPKH_ADDRESS);
+
+        // ECDSA signatures are deterministic, this is the vector without low R grinding
+        let signature = sign(&account, "Hello World", SignatureFormat::Simple).unwrap();
+        assert_eq!(
+            signature,
+            "AkgwRQIhAOzyynlqt93lOKJr+wmmxIens//zPzl9tqIOua93wO6MAiBi5n5EyAcPScOjf1lAqIUIQtr3zKNeavYabHyR8eGhowEhAsfxIAMZZEKUPYWI4BruhAQjzFT8FSFSajuFwrDL1Yhy"
+        );
+        verify(P2WPKH_ADDRESS, "Hello World", &signature).unwrap();
+        verify(
+            P2WPKH_ADDRESS,
+            "Hello World",
+            "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=",
+        )
+        .unwrap();
+        verify(
+            P2WPKH_ADDRESS,
+            "",
+            "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=",
+        )
+        .unwrap();
+
+        verify(
+            "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3",
+            "Hello World",
+            "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            verify(P2WPKH_ADDRESS, "Hello World!", &signature),
+            Err(AccountError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_sign_and_verify_every_address_type() {
+        let mnemonic = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let other = Account::from_mnemonic(Network::Bitcoin, mnemonic, AddressType::P2WPKH, Some([0, 1])).unwrap();
+        for address_type in AddressType::ALL {
+            let account = Account::from_mnemonic(Network::Bitcoin, mnemonic, address_type, None).unwrap();
+            let address = account.address.to_string();
+            for format in [SignatureFormat::Simple, SignatureFormat::Full] {
+                if format == SignatureFormat::Simple && address_type == AddressType::P2PKH {
+                    assert!(sign(&account, "proof", format).is_err());
+                    continue;
+                }
+                let signature = sign(&account, "proof", format).unwrap();
+                verify(&address, "proof", &signature).unwrap();
+                assert!(verify(&address, "other proof", &signature).is_err());
+                assert!(verify(&other.address.to_string(), "proof", &signature).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_garbage() {
+        assert!(matches!(
+            verify(P2WPKH_ADDRESS, "Hello World", "not base64!"),
+            Err(AccountError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            verify(P2WPKH_ADDRESS, "Hello World", "AAAA"),
+            Err(AccountError::InvalidSignature(_))
+        ));
+        // P2WSH needs a script interpreter
+        let signature = sign(&Account::from_wif(WIF, Network::Bitcoin, AddressType::P2WPKH).unwrap(), "", SignatureFormat::Simple).unwrap();
+        assert!(matches!(
+            verify("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3", "", &signature),
+            Err(AccountError::InvalidSignature(_))
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+
+use bitcoin::{Amount, FeeRate, OutPoint};
+
+use crate::account::address_type::AddressType;
+use crate::errors::{AccountError, Result};
+use crate::psbt::UnSignedPsbt;
+use crate::sat_flow::inscription_flows;
+use crate::utils::UnspentOutput;
+
+/// Outputs below this value are not worth creating, leftover change under it goes to the fee.
+pub const DUST_LIMIT: Amount = Amount::from_sat(546);
+
+/// version, locktime, input/output counts and segwit marker, rounded up
+pub(crate) const TX_OVERHEAD_VBYTES: u64 = 11;
+
+/// size of the largest standard output we create (P2TR)
+pub(crate) const OUTPUT_VBYTES: u64 = 43;
+
+pub(crate) fn input_vbytes(address_type: &AddressType) -> u64 {
+    match address_type {
+        AddressType::P2PKH => 148,
+        AddressType::P2SH_P2WPKH => 91,
+        AddressType::P2WPKH => 68,
+        AddressType::P2TR => 58,
+    }
+}
+
+/// Which asset-bearing utxos coin selection is allowed to spend.
+/// By default utxos carrying inscriptions or runes are never selected.
+#[derive(Debug, Clone, Default)]
+pub struct AssetPolicy {
+    requested: Vec<OutPoint>,
+}
+
+impl AssetPolicy {
+    pub fn new() -> Self {
+        AssetPolicy::default()
+    }
+
+    /// Requests `outpoint` to be spent, e.g. because its inscription is being sent. Requesting
+    /// the same outpoint again has no effect.
+    pub fn request(mut self, outpoint: OutPoint) -> Self {
+        if !self.is_requested(&outpoint) {
+            self.requested.push(outpoint);
+        }
+        self
+    }
+
+    pub fn is_requested(&self, outpoint: &OutPoint) -> bool {
+        self.requested.contains(outpoint)
+    }
+}
+
+pub struct Selection<'a> {
+    pub inputs: Vec<&'a UnspentOutput>,
+    pub fee: Amount,
+    /// Zero when the leftover was too small for a change output.
+    pub change: Amount,
+}
+
+/// Selects utxos paying `target` to `outputs` recipient outputs at `fee_rate`.
+///
+/// Utxos requested by `policy` are always spent first. The rest of the funding comes from
+/// utxos without inscriptions or runes, largest first.
+pub fn select_utxos<'a>(
+    utxos: &'a [UnspentOutput],
+    policy: &AssetPolicy,
+    target: Amount,
+    outputs: usize,
+    fee_rate: FeeRate,
+) -> Result<Selection<'a>> {
+    let mut inputs = Vec::new();
+    for outpoint in &policy.requested {
+        inputs.push(find_utxo(utxos, outpoint)?);
+    }
+
+    // a utxo listed twice is still only spent once
+    let mut seen: HashSet<OutPoint> = policy.requested.iter().copied().collect();
+    let mut candidates: Vec<&UnspentOutput> = utxos
+        .iter()
+        .filter(|utxo| !utxo.has_assets() && seen.insert(utxo.outpoint()))
+        .collect();
+    candidates.sort_by_key(|utxo| std::cmp::Reverse(utxo.satoshis));
+    let available: Amount = inputs
+        .iter()
+        .chain(candidates.iter())
+        .map(|utxo| utxo.satoshis)
+        .sum();
+    let mut candidates = candidates.into_iter();
+
+    let fee = |inputs: &[&UnspentOutput], outputs: u64| -> Result<Amount> {
+        let vbytes = TX_OVERHEAD_VBYTES
+            + inputs.iter().map(|utxo| input_vbytes(&utxo.address_type)).sum::<u64>()
+            + outputs * OUTPUT_VBYTES;
+        fee_rate
+            .fee_vb(vbytes)
+            .ok_or_else(|| AccountError::Other("fee overflow".to_string()))
+    };
+
+    loop {
+        let total: Amount = inputs.iter().map(|utxo| utxo.satoshis).sum();
+
+        let fee_with_change = fee(&inputs, outputs as u64 + 1)?;
+        if total >= target + fee_with_change + DUST_LIMIT {
+            return Ok(Selection {
+                change: total - target - fee_with_change,
+                fee: fee_with_change,
+                inputs,
+            });
+        }
+
+        let fee_without_change = fee(&inputs, outputs as u64)?;
+        if total >= target + fee_without_change {
+            return Ok(Selection {
+                change: Amount::ZERO,
+                fee: total - target,
+                inputs,
+            });
+        }
+
+        match candidates.next() {
+            Some(utxo) => inputs.push(utxo),
+            None => {
+                return Err(AccountError::InsufficientFunds {
+                    required: (target + fee_without_change).to_sat(),
+                    available: available.to_sat(),
+                })
+            }
+        }
+    }
+}
+
+/// Fails if an input utxo carries assets that were not requested by `policy`.
+pub fn check_asset_inputs(psbt: &UnSignedPsbt, utxos: &[UnspentOutput], policy: &AssetPolicy) -> Result<()> {
+    for input in &psbt.inputs {
+        let utxo = find_utxo(utxos, &input.previous_output)?;
+        if utxo.has_assets() && !policy.is_requested(&input.previous_output) {
+            return Err(AccountError::AssetUtxo(input.previous_output.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every inscription spent by `psbt` lands in the output given for it in `expected`.
+///
+/// Fails if an inscription would be paid as fee, would land in another output, or has no
+/// expected output at all. See [`inscription_flows`] for how sats move through the transaction.
+pub fn verify_inscription_transfers(
+    psbt: &UnSignedPsbt,
+    utxos: &[UnspentOutput],
+    expected: &[(&str, u32)],
+) -> Result<()> {
+    for flow in inscription_flows(psbt, utxos)? {
+        let id = flow.inscription_id.as_str();
+        let actual = flow
+            .vout()
+            .ok_or_else(|| AccountError::InscriptionInFee(id.to_string()))?;
+
+        match expected.iter().find(|(expected_id, _)| *expected_id == id) {
+            Some((_, vout)) if *vout == actual => {}
+            Some((_, vout)) => {
+                return Err(AccountError::InscriptionMisplaced(format!(
+                    "{id} lands in output {actual}, expected {vout}"
+                )))
+            }
+            None => {
+                return Err(AccountError::InscriptionMisplaced(format!(
+                    "{id} lands in output {actual} but was not requested"
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn find_utxo<'a>(utxos: &'a [UnspentOutput], outpoint: &OutPoint) -> Result<&'a UnspentOutput> {
+    utxos
+        .iter()
+        .find(|utxo| utxo.outpoint() == *outpoint)
+        .ok_or_else(|| AccountError::Other(format!("missing utxo for input {outpoint}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Network;
+
+    use super::*;
+    use crate::account::account::Account;
+    use crate::runes::rune_id::RuneId;
+    use crate::utils::{dummy_inscription_utxo, dummy_rune_utxo, dummy_utxos};
+
+    const MNEMONIC: &str = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    fn account() -> Account {
+        Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, None).unwrap()
+    }
+
+    fn spend(psbt: UnSignedPsbt, utxo: &UnspentOutput) -> UnSignedPsbt {
+        psbt.add_input(&utxo.tx_id.to_string(), utxo.vout)
+    }
+
+    #[test]
+    fn test_select_skips_asset_utxos() {
+        let account = account();
+        let plain = dummy_utxos(&account, &[Amount::from_sat(20_000), Amount::from_sat(50_000)]);
+        let utxos = vec![
+            dummy_inscription_utxo(&account, Amount::from_sat(1_000_000)),
+            plain[0].clone(),
+            dummy_rune_utxo(&account, Amount::from_sat(1_000_000), RuneId::new(840000, 1).unwrap(), 100),
+            plain[1].clone(),
+        ];
+
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let selection =
+            select_utxos(&utxos, &AssetPolicy::new(), Amount::from_sat(30_000), 1, fee_rate).unwrap();
+
+        assert_eq!(selection.inputs.len(), 1);
+        assert_eq!(selection.inputs[0].outpoint(), plain[1].outpoint());
+        assert_eq!(selection.fee + selection.change + Amount::from_sat(30_000), Amount::from_sat(50_000));
+
+        let result = select_utxos(&utxos, &AssetPolicy::new(), Amount::from_sat(100_000), 1, fee_rate);
+        assert!(matches!(result, Err(AccountError::InsufficientFunds { available: 70_000, .. })));
+    }
+
+    #[test]
+    fn test_select_spends_requested_asset_utxo_first() {
+        let account = account();
+        let inscribed = dummy_inscription_utxo(&account, Amount::from_sat(546));
+        let mut utxos = dummy_utxos(&account, &[Amount::from_sat(20_000)]);
+        utxos.push(inscribed.clone());
+
+        let policy = AssetPolicy::new().request(inscribed.outpoint());
+        let selection = select_utxos(
+            &utxos,
+            &policy,
+            Amount::from_sat(546),
+            1,
+            FeeRate::from_sat_per_vb(1).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(selection.inputs.len(), 2);
+        assert_eq!(selection.inputs[0].outpoint(), inscribed.outpoint());
+        assert_eq!(selection.inputs[1].outpoint(), utxos[0].outpoint());
+    }
+
+    #[test]
+    fn test_select_spends_each_outpoint_once() {
+        let account = account();
+        let inscribed = dummy_inscription_utxo(&account, Amount::from_sat(546));
+        let plain = dummy_utxos(&account, &[Amount::from_sat(20_000)]);
+        let utxos = vec![plain[0].clone(), inscribed.clone(), plain[0].clone()];
+
+        let policy = AssetPolicy::new().request(inscribed.outpoint()).request(inscribed.outpoint());
+        let fee_rate = FeeRate::from_sat_per_vb(1).unwrap();
+        let selection = select_utxos(&utxos, &policy, Amount::from_sat(546), 1, fee_rate).unwrap();
+        assert_eq!(selection.inputs.len(), 2);
+        assert_ne!(selection.inputs[0].outpoint(), selection.inputs[1].outpoint());
+
+        let result = select_utxos(&utxos, &AssetPolicy::new(), Amount::from_sat(30_000), 1, fee_rate);
+        assert!(matches!(result, Err(AccountError::InsufficientFunds { available: 20_000, .. })));
+    }
+
+    #[test]
+    fn test_check_asset_inputs() {
+        let account = account();
+        let inscribed = dummy_inscription_utxo(&account, Amount::from_sat(546));
+        let mut utxos = dummy_utxos(&account, &[Amount::from_sat(20_000)]);
+        utxos.push(inscribed.clone());
+        let psbt = spend(spend(UnSignedPsbt::new(), &inscribed), &utxos[0])
+            .add_output(&account.address.to_string(), 546);
+
+        assert!(matches!(
+            check_asset_inputs(&psbt, &utxos, &AssetPolicy::new()),
+            Err(AccountError::AssetUtxo(_))
+        ));
+        assert!(check_asset_inputs(&psbt, &utxos, &AssetPolicy::new().request(inscribed.outpoint())).is_ok());
+    }
+
+    #[test]
+    fn test_verify_inscription_transfers() {
+        let account = account();
+        let address = account.address.to_string();
+        let inscribed = dummy_inscription_utxo(&account, Amount::from_sat(546));
+        let id = inscribed.inscriptions[0].inscription_id.as_str();
+        let mut utxos = dummy_utxos(&account, &[Amount::from_sat(10_000)]);
+        utxos.push(inscribed.clone());
+        let inputs = spend(spend(UnSignedPsbt::new(), &utxos[0]), &inscribed);
+
+        // the inscription sits on sat 10_000 of the inputs, which falls in the second output
+        let psbt = inputs.clone().add_output(&address, 9_000).add_output(&address, 1_500);
+        assert!(verify_inscription_transfers(&psbt, &utxos, &[(id, 1)]).is_ok());
+        assert!(matches!(
+            verify_inscription_transfers(&psbt, &utxos, &[(id, 0)]),
+            Err(AccountError::InscriptionMisplaced(_))
+        ));
+        assert!(matches!(
+            verify_inscription_transfers(&psbt, &utxos, &[]),
+            Err(AccountError::InscriptionMisplaced(_))
+        ));
+
+        // outputs only cover the first input, so the inscription goes to the miner
+        let psbt = inputs.add_output(&address, 9_000);
+        assert!(matches!(
+            verify_inscription_transfers(&psbt, &utxos, &[(id, 0)]),
+            Err(AccountError::InscriptionInFee(_))
+        ));
+    }
+}
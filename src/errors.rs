@@ -6,8 +6,66 @@ pub enum AccountError {
     InvalidAddressType(String),
     #[error("Invalid mnemonic")]
     InvalidMnemonic,
+    #[error("Unknown mnemonic word at position {position}, closest words: {suggestions:?}")]
+    UnknownMnemonicWord { position: usize, suggestions: Vec<String> },
+    #[error("Invalid mnemonic checksum, a word is wrong or words are out of order")]
+    InvalidMnemonicChecksum,
+    #[error("Invalid mnemonic word count: {0}, expected one of 12, 15, 18, 21, 24")]
+    InvalidWordCount(usize),
+    #[error("Invalid entropy: {0}")]
+    InvalidEntropy(String),
     #[error("Invalid HD path: {0}")]
     InvalidHDPath(String),
+    #[error("Invalid rune name: {0}")]
+    InvalidRune(String),
+    #[error("Invalid rune id: {0}")]
+    InvalidRuneId(String),
+    #[error("Invalid varint: {0}")]
+    InvalidVarint(String),
+    #[error("Insufficient funds: required {required} sats, available {available} sats")]
+    InsufficientFunds { required: u64, available: u64 },
+    #[error("Refusing to spend asset-bearing utxo: {0}")]
+    AssetUtxo(String),
+    #[error("Inscription would be spent as fee: {0}")]
+    InscriptionInFee(String),
+    #[error("Inscription would land in the wrong output: {0}")]
+    InscriptionMisplaced(String),
+    #[error("Wrong password or corrupted keystore")]
+    KeystoreDecryption,
+    #[error("Unsupported keystore version: {0}")]
+    UnsupportedKeystoreVersion(u32),
+    #[error("Invalid keystore: {0}")]
+    InvalidKeystore(String),
+    #[error("Keystore requires a BIP-39 passphrase")]
+    PassphraseRequired,
+    #[error("Keystore was saved without a BIP-39 passphrase")]
+    PassphraseNotExpected,
+    #[error("Keystore scrypt cost is too high: {0}")]
+    KeystoreCostTooHigh(String),
+    #[error("Invalid BIP-38 key: {0}")]
+    InvalidBip38(String),
+    #[error("Wrong BIP-38 passphrase")]
+    Bip38WrongPassphrase,
+    #[error("Unsupported language: {0}")]
+    UnsupportedLanguage(String),
+    #[error("Invalid extended key: {0}")]
+    InvalidExtendedKey(String),
+    #[error("Invalid SLIP-39 shares: {0}")]
+    InvalidSlip39(String),
+    #[error("Invalid private key: {0}")]
+    InvalidPrivateKey(String),
+    #[error("Signing failed: {0}")]
+    Signing(String),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Utxo is not spendable by this key: {0}")]
+    ForeignUtxo(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid address checksum: {0}")]
+    InvalidAddressChecksum(String),
+    #[error("Address mixes upper and lower case: {0}")]
+    InvalidAddressCase(String),
     #[error("Other error: {0}")]
     Other(String),
 
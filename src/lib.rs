@@ -1,4 +1,11 @@
 pub mod account;
+pub mod address;
+pub mod bip322;
+pub mod coin_selection;
 pub mod errors;
 pub mod psbt;
+pub mod runes;
+pub mod sat_flow;
+pub mod secp;
+pub mod sweep;
 pub mod utils;
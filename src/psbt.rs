@@ -1,14 +1,24 @@
+use std::fmt;
 use std::str::FromStr;
 
-use bitcoin::{
-    Address, Amount, OutPoint, Psbt, PublicKey, ScriptBuf, secp256k1::Secp256k1, Transaction, TxIn,
-    TxOut, Witness,
-};
+use bitcoin::{Address, Amount, CompressedPublicKey, OutPoint, Psbt, PublicKey, ScriptBuf, TapSighashType, Transaction, TxIn, TxOut, Witness, XOnlyPublicKey};
 use bitcoin::absolute::LockTime;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::ecdsa;
 use bitcoin::hashes::Hash;
+use bitcoin::key::{Keypair, TapTweak};
+use bitcoin::script::{Builder, PushBytesBuf};
+use bitcoin::secp256k1::Message;
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache};
+use bitcoin::taproot;
 use bitcoin::transaction::Version;
 
 use crate::account::account::Account;
+use crate::account::address_type::AddressType;
+use crate::errors::{AccountError, Result};
+use crate::runes::runestone::Runestone;
+use crate::secp;
+use crate::utils::UnspentOutput;
 
 #[derive(Debug, Clone)]
 pub struct UnSignedPsbt {
@@ -22,6 +32,12 @@ pub struct WitnessUtxo {
     pub value: Amount,
 }
 
+impl Default for UnSignedPsbt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UnSignedPsbt {
     pub fn new() -> Self {
         UnSignedPsbt {
@@ -34,22 +50,24 @@ impl UnSignedPsbt {
     pub fn add_input(mut self, txid: &str, vout: u32) -> Self {
         let prev_tx_id = txid.parse().unwrap();
         let input = TxIn {
-            /// The reference to the previous output that is being used as an input.
+            // The reference to the previous output that is being used as an input.
             previous_output: OutPoint {
                 txid: prev_tx_id,
                 vout,
             },
-            /// The script which pushes values on the stack which will cause the referenced output’s script to be accepted.
+            // The script which pushes values on the stack which will cause the referenced output’s script to be accepted.
             script_sig: ScriptBuf::new(),
-            /// The sequence number, which suggests to miners which of two conflicting transactions should be preferred, or 0xFFFFFFFF to ignore this feature. This is generally never used since the miner behavior cannot be enforced.
+            // The sequence number, which suggests to miners which of two conflicting transactions should be preferred, or 0xFFFFFFFF to ignore this feature. This is generally never used since the miner behavior cannot be enforced.
             sequence: bitcoin::Sequence::from_consensus(0xffffffff),
-            /// Witness data: an array of byte-arrays. Note that this field is not (de)serialized with the rest of the TxIn in Encodable/Decodable, as it is (de)serialized at the end of the full Transaction. It is (de)serialized with the rest of the TxIn in other (de)serialization routines.
+            // Witness data: an array of byte-arrays. Note that this field is not (de)serialized with the rest of the TxIn in Encodable/Decodable, as it is (de)serialized at the end of the full Transaction. It is (de)serialized with the rest of the TxIn in other (de)serialization routines.
             witness: Witness::new(),
         };
         self.inputs.push(input);
         self
     }
 
+    /// Adds an output paying `amount` sats to `address`. Panics if `address` does not parse,
+    /// validate user input with [`crate::address::parse_address`] first.
     pub fn add_output(mut self, address: &str, amount: u64) -> Self {
         let recipient_address = Address::from_str(address).unwrap().assume_checked();
         let output = TxOut {
@@ -60,47 +78,294 @@ impl UnSignedPsbt {
         self
     }
 
+    /// Adds a zero-value OP_RETURN output carrying `runestone`.
+    pub fn add_runestone(mut self, runestone: &Runestone) -> Self {
+        let output = TxOut {
+            value: Amount::ZERO,
+            script_pubkey: runestone.encipher(),
+        };
+        self.outputs.push(output);
+        self
+    }
+
     pub fn add_signer(mut self, public_key: &[u8]) -> Self {
         let public_key = PublicKey::from_slice(public_key).unwrap();
         self.public_keys.push(public_key);
         self
     }
 
+    /// The transaction as a PSBT with the spent output of every input taken from `utxos`, ready
+    /// for [`sign_psbt`].
+    pub fn to_psbt(&self, utxos: &[UnspentOutput]) -> Result<Psbt> {
+        let mut psbt = Psbt::from_unsigned_tx(self.clone().build())
+            .map_err(|e| AccountError::Other(e.to_string()))?;
+
+        for (txin, input) in self.inputs.iter().zip(psbt.inputs.iter_mut()) {
+            let utxo = utxos
+                .iter()
+                .find(|utxo| utxo.outpoint() == txin.previous_output)
+                .ok_or_else(|| {
+                    AccountError::Other(format!("missing utxo for input {}", txin.previous_output))
+                })?;
+
+            // legacy inputs commit to no amount, BIP-174 wants the whole previous transaction
+            // for them and a witness utxo only for segwit inputs
+            let legacy = utxo.script_publkey.is_p2pkh();
+            if !legacy {
+                input.witness_utxo = Some(TxOut {
+                    value: utxo.satoshis,
+                    script_pubkey: utxo.script_publkey.clone(),
+                });
+            }
+            match &utxo.raw_tx {
+                Some(raw_tx) => {
+                    let tx: Transaction =
+                        deserialize_hex(raw_tx).map_err(|e| AccountError::Other(e.to_string()))?;
+                    if tx.compute_txid() != utxo.tx_id {
+                        return Err(AccountError::Other(format!(
+                            "raw_tx of {} has the wrong txid",
+                            utxo.outpoint()
+                        )));
+                    }
+                    input.non_witness_utxo = Some(tx);
+                }
+                None if legacy => {
+                    return Err(AccountError::Other(format!(
+                        "P2PKH input {} needs its raw_tx",
+                        utxo.outpoint()
+                    )))
+                }
+                None => {}
+            }
+        }
+
+        Ok(psbt)
+    }
+
     pub fn build(self) -> Transaction {
-        let unsigned_tx = Transaction {
+        Transaction {
             version: Version::TWO,
             lock_time: LockTime::ZERO,
             input: self.inputs,
             output: self.outputs,
+        }
+    }
+}
+
+/// Looks up the output spent by input `index`, from `witness_utxo` or `non_witness_utxo`.
+fn spent_output(psbt: &Psbt, index: usize) -> Result<TxOut> {
+    let input = &psbt.inputs[index];
+    if let Some(witness_utxo) = &input.witness_utxo {
+        return Ok(witness_utxo.clone());
+    }
+    let outpoint = psbt.unsigned_tx.input[index].previous_output;
+    input
+        .non_witness_utxo
+        .as_ref()
+        .and_then(|tx| tx.output.get(outpoint.vout as usize))
+        .cloned()
+        .ok_or_else(|| AccountError::Signing(format!("missing utxo for input {}", index)))
+}
+
+/// Signs every input of `psbt` paying to `account.address` and returns how many were signed.
+///
+/// Inputs of other addresses are left alone so several accounts can sign the same PSBT. Taproot
+/// inputs are signed on the key path and need the spent output of every input.
+pub fn sign_psbt(psbt: &mut Psbt, account: &Account) -> Result<usize> {
+    let secp = secp::context();
+    let private_key = account.private_key()?;
+    let script_pubkey = account.address.script_pubkey();
+
+    let spent = (0..psbt.inputs.len())
+        .map(|index| spent_output(psbt, index))
+        .collect::<Vec<_>>();
+    let mut cache = SighashCache::new(psbt.unsigned_tx.clone());
+
+    let mut signed = 0;
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        let Ok(utxo) = &spent[index] else { continue };
+        if utxo.script_pubkey != script_pubkey {
+            continue;
+        }
+
+        match account.address_type {
+            AddressType::P2PKH => {
+                let sighash = cache
+                    .legacy_signature_hash(index, &utxo.script_pubkey, EcdsaSighashType::All.to_u32())
+                    .map_err(|e| AccountError::Signing(e.to_string()))?;
+                let message = Message::from_digest(sighash.to_byte_array());
+                let signature = ecdsa::Signature::sighash_all(secp.sign_ecdsa(&message, &private_key.inner));
+                input.partial_sigs.insert(account.public_key, signature);
+            }
+            AddressType::P2SH_P2WPKH | AddressType::P2WPKH => {
+                let compressed = CompressedPublicKey(account.public_key.inner);
+                let witness_script = ScriptBuf::new_p2wpkh(&compressed.wpubkey_hash());
+                let sighash = cache
+                    .p2wpkh_signature_hash(index, &witness_script, utxo.value, EcdsaSighashType::All)
+                    .map_err(|e| AccountError::Signing(e.to_string()))?;
+                let message = Message::from_digest(sighash.to_byte_array());
+                let signature = ecdsa::Signature::sighash_all(secp.sign_ecdsa(&message, &private_key.inner));
+                input.partial_sigs.insert(account.public_key, signature);
+                if account.address_type == AddressType::P2SH_P2WPKH {
+                    input.redeem_script = Some(witness_script);
+                }
+            }
+            AddressType::P2TR => {
+                let prevouts = spent
+                    .iter()
+                    .map(|utxo| utxo.as_ref().map_err(|e| AccountError::Signing(e.to_string())))
+                    .collect::<Result<Vec<_>>>()?;
+                let sighash = cache
+                    .taproot_key_spend_signature_hash(index, &Prevouts::All(&prevouts), TapSighashType::Default)
+                    .map_err(|e| AccountError::Signing(e.to_string()))?;
+                let message = Message::from_digest(sighash.to_byte_array());
+                let keypair = Keypair::from_secret_key(secp, &private_key.inner);
+                let tweaked = keypair.tap_tweak(secp, None);
+                let signature = secp.sign_schnorr_with_rng(&message, &tweaked.to_inner(), &mut rand::thread_rng());
+                input.tap_key_sig = Some(taproot::Signature {
+                    signature,
+                    sighash_type: TapSighashType::Default,
+                });
+                input.tap_internal_key = Some(keypair.x_only_public_key().0);
+            }
+        }
+        signed += 1;
+    }
+
+    Ok(signed)
+}
+
+/// Turns the signatures of a fully signed `psbt` into script sigs and witnesses and extracts
+/// the transaction.
+pub fn finalize_psbt(mut psbt: Psbt) -> Result<Transaction> {
+    for index in 0..psbt.inputs.len() {
+        let utxo = spent_output(&psbt, index)?;
+        let input = &mut psbt.inputs[index];
+
+        if let Some(signature) = input.tap_key_sig {
+            input.final_script_witness = Some(Witness::p2tr_key_spend(&signature));
+        } else if let Some((public_key, signature)) = input.partial_sigs.iter().next() {
+            if let Some(redeem_script) = &input.redeem_script {
+                input.final_script_sig = Some(p2sh_script_sig(redeem_script)?);
+                input.final_script_witness = Some(Witness::p2wpkh(signature, &public_key.inner));
+            } else if utxo.script_pubkey.is_p2wpkh() {
+                input.final_script_witness = Some(Witness::p2wpkh(signature, &public_key.inner));
+            } else {
+                input.final_script_sig = Some(
+                    Builder::new()
+                        .push_slice(signature.serialize())
+                        .push_key(public_key)
+                        .into_script(),
+                );
+            }
+        } else {
+            return Err(AccountError::Signing(format!("input {} is not signed", index)));
+        }
+
+        input.partial_sigs.clear();
+        input.redeem_script = None;
+        input.tap_key_sig = None;
+    }
+
+    Ok(psbt.extract_tx_unchecked_fee_rate())
+}
+
+/// Checks the signature of every input of `tx` against `prevouts`, the outputs it spends in
+/// order.
+///
+/// Only the single key spends [`sign_psbt`] produces are understood, there is no script
+/// interpreter, so any other script is reported as unverifiable.
+pub fn verify_spend(tx: &Transaction, prevouts: &[TxOut]) -> Result<()> {
+    if tx.input.len() != prevouts.len() {
+        return Err(AccountError::InvalidSignature("one spent output per input is needed".to_string()));
+    }
+    let secp = secp::context();
+    let mut cache = SighashCache::new(tx);
+
+    for (index, (txin, utxo)) in tx.input.iter().zip(prevouts).enumerate() {
+        let spk = &utxo.script_pubkey;
+        if spk.is_p2tr() {
+            if !txin.script_sig.is_empty() || txin.witness.len() != 1 {
+                return Err(invalid_input(index, "not a key path spend"));
+            }
+            let signature =
+                taproot::Signature::from_slice(&txin.witness[0]).map_err(|e| invalid_input(index, e))?;
+            let output_key = XOnlyPublicKey::from_slice(&spk.as_bytes()[2..]).map_err(|e| invalid_input(index, e))?;
+            let sighash = cache
+                .taproot_key_spend_signature_hash(index, &Prevouts::All(prevouts), signature.sighash_type)
+                .map_err(|e| invalid_input(index, e))?;
+            let message = Message::from_digest(sighash.to_byte_array());
+            secp.verify_schnorr(&signature.signature, &message, &output_key)
+                .map_err(|_| invalid_input(index, "wrong signature"))?;
+            continue;
+        }
+
+        let (signature, public_key, script_code) = if spk.is_p2pkh() {
+            let pushes = txin
+                .script_sig
+                .instructions()
+                .map(|instruction| Some(instruction.ok()?.push_bytes()?.as_bytes().to_vec()))
+                .collect::<Option<Vec<_>>>();
+            match pushes.as_deref() {
+                Some([signature, public_key]) if txin.witness.is_empty() => {
+                    (signature.clone(), public_key.clone(), None)
+                }
+                _ => return Err(invalid_input(index, "expected a signature and a public key")),
+            }
+        } else if spk.is_p2wpkh() || spk.is_p2sh() {
+            if txin.witness.len() != 2 {
+                return Err(invalid_input(index, "expected a signature and a public key"));
+            }
+            let public_key = CompressedPublicKey::from_slice(&txin.witness[1]).map_err(|e| invalid_input(index, e))?;
+            let script_code = ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash());
+            let (expected_spk, expected_script_sig) = if spk.is_p2sh() {
+                (script_code.to_p2sh(), p2sh_script_sig(&script_code)?)
+            } else {
+                (script_code.clone(), ScriptBuf::new())
+            };
+            if *spk != expected_spk || txin.script_sig != expected_script_sig {
+                return Err(invalid_input(index, "public key does not match the spent script"));
+            }
+            (txin.witness[0].to_vec(), txin.witness[1].to_vec(), Some(script_code))
+        } else {
+            return Err(invalid_input(index, format!("cannot verify spends of {}", spk)));
+        };
+
+        let public_key = PublicKey::from_slice(&public_key).map_err(|e| invalid_input(index, e))?;
+        let signature = ecdsa::Signature::from_slice(&signature).map_err(|e| invalid_input(index, e))?;
+        let sighash = match script_code {
+            None => {
+                if *spk != ScriptBuf::new_p2pkh(&public_key.pubkey_hash()) {
+                    return Err(invalid_input(index, "public key does not match the spent script"));
+                }
+                cache
+                    .legacy_signature_hash(index, spk, signature.sighash_type.to_u32())
+                    .map_err(|e| invalid_input(index, e))?
+                    .to_byte_array()
+            }
+            Some(script_code) => cache
+                .p2wpkh_signature_hash(index, &script_code, utxo.value, signature.sighash_type)
+                .map_err(|e| invalid_input(index, e))?
+                .to_byte_array(),
         };
-        unsigned_tx
+        let message = Message::from_digest(sighash);
+        secp.verify_ecdsa(&message, &signature.signature, &public_key.inner)
+            .map_err(|_| invalid_input(index, "wrong signature"))?;
     }
+
+    Ok(())
 }
 
-// pub fn sign_psbt(
-//     unsigned_tx: Transaction,
-//     updater_account: &Account,
-//     sender_account: &Account,
-// ) -> Result<Transaction> {
-//     let secp = Secp256k1::new();
-
-//     let mut psbt = Psbt::from_unsigned_tx(unsigned_tx);
-
-//     // updater
-//     // updater
-//     let mut input = bitcoin::psbt::Input {
-//         witness_utxo: Some(TxOut {
-//             value: Amount::from_sat(100_000),
-//             script_pubkey: ScriptBuf::from(vec![0x76, 0xa9, 0x14, 0x0, 0x88, 0xac]),
-//         }),
-//         ..Default::default()
-//     };
-//     let publick_key = updater_account.xpub.to_pub();
-//     let wpkh = publick_key.wpubkey_hash();
-
-//     let redeem_script = ScriptBuf::new_p2wpkh(&wpkh);
-//     input.redeem_script = Some(redeem_script);
-// }
+/// The script sig of a P2SH-P2WPKH input, a single push of its `redeem_script`.
+pub(crate) fn p2sh_script_sig(redeem_script: &ScriptBuf) -> Result<ScriptBuf> {
+    let redeem_script =
+        PushBytesBuf::try_from(redeem_script.to_bytes()).map_err(|e| AccountError::Signing(e.to_string()))?;
+    Ok(Builder::new().push_slice(redeem_script).into_script())
+}
+
+fn invalid_input(index: usize, reason: impl fmt::Display) -> AccountError {
+    AccountError::InvalidSignature(format!("input {}: {}", index, reason))
+}
 
 #[cfg(test)]
 mod tests {
@@ -117,6 +382,111 @@ mod tests {
             .add_output("mv4rnyY3Su5gjcDNzbMLKBQkBicCtHUtFB", 50_000)
             .add_signer(&hex::decode(public_key).unwrap())
             .build();
-        assert!(true);
+        assert_eq!(unsigned_psbt.input.len(), 1);
+        assert_eq!(unsigned_psbt.output.len(), 1);
+    }
+
+    #[test]
+    fn test_build_rune_transfer() {
+        use crate::runes::rune_id::RuneId;
+        use crate::runes::runestone::{Artifact, Edict};
+
+        let runestone = Runestone {
+            edicts: vec![Edict {
+                id: RuneId::new(840000, 1).unwrap(),
+                amount: 100,
+                output: 1,
+            }],
+            ..Default::default()
+        };
+        let unsigned_tx = UnSignedPsbt::new()
+            .add_input(
+                "7f6eea7dfcc5bb1e443e63e86c6a63d0b8ed9c367f6b9e4f1e5d6e4f4e3d2c1b",
+                0,
+            )
+            .add_runestone(&runestone)
+            .add_output("mv4rnyY3Su5gjcDNzbMLKBQkBicCtHUtFB", 546)
+            .build();
+
+        assert_eq!(
+            Runestone::decipher(&unsigned_tx),
+            Some(Artifact::Runestone(runestone))
+        );
+    }
+
+    #[test]
+    fn test_sign_psbt_every_address_type() {
+        use bitcoin::Network;
+        use crate::utils::dummy_utxos;
+
+        let mnemonic = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        for address_type in [
+            AddressType::P2PKH,
+            AddressType::P2SH_P2WPKH,
+            AddressType::P2WPKH,
+            AddressType::P2TR,
+        ] {
+            let account = Account::from_mnemonic(Network::Bitcoin, mnemonic, address_type, None).unwrap();
+            let utxos = dummy_utxos(&account, &[Amount::from_sat(20_000), Amount::from_sat(30_000)]);
+
+            let mut unsigned = UnSignedPsbt::new();
+            for utxo in &utxos {
+                unsigned = unsigned.add_input(&utxo.tx_id.to_string(), utxo.vout);
+            }
+            let unsigned = unsigned.add_output(&account.address.to_string(), 49_000);
+
+            let mut psbt = unsigned.to_psbt(&utxos).unwrap();
+            let legacy = address_type == AddressType::P2PKH;
+            assert!(psbt.inputs.iter().all(|input| input.witness_utxo.is_none() == legacy));
+            assert!(psbt.inputs.iter().all(|input| input.non_witness_utxo.is_some()));
+            assert_eq!(sign_psbt(&mut psbt, &account).unwrap(), 2);
+            let tx = finalize_psbt(psbt).unwrap();
+
+            let prevouts = utxos
+                .iter()
+                .map(|utxo| TxOut { value: utxo.satoshis, script_pubkey: utxo.script_publkey.clone() })
+                .collect::<Vec<_>>();
+            verify_spend(&tx, &prevouts).unwrap();
+
+            if legacy {
+                let mut without_raw_tx = utxos.clone();
+                without_raw_tx[0].raw_tx = None;
+                assert!(unsigned.to_psbt(&without_raw_tx).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign_psbt_with_imported_key_and_several_signers() {
+        use bitcoin::Network;
+        use crate::utils::dummy_utxos;
+
+        let mnemonic = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+        let hd = Account::from_mnemonic(Network::Bitcoin, mnemonic, AddressType::P2TR, None).unwrap();
+        let imported = Account::from_wif(
+            "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR",
+            Network::Bitcoin,
+            AddressType::P2PKH,
+        )
+        .unwrap();
+
+        let mut utxos = dummy_utxos(&hd, &[Amount::from_sat(20_000)]);
+        utxos.extend(dummy_utxos(&imported, &[Amount::from_sat(30_000)]));
+        let unsigned = UnSignedPsbt::new()
+            .add_input(&utxos[0].tx_id.to_string(), utxos[0].vout)
+            .add_input(&utxos[1].tx_id.to_string(), utxos[1].vout)
+            .add_output(&hd.address.to_string(), 49_000);
+
+        let mut psbt = unsigned.to_psbt(&utxos).unwrap();
+        assert_eq!(sign_psbt(&mut psbt, &hd).unwrap(), 1);
+        assert!(finalize_psbt(psbt.clone()).is_err());
+        assert_eq!(sign_psbt(&mut psbt, &imported).unwrap(), 1);
+        let tx = finalize_psbt(psbt).unwrap();
+
+        let prevouts = utxos
+            .iter()
+            .map(|utxo| TxOut { value: utxo.satoshis, script_pubkey: utxo.script_publkey.clone() })
+            .collect::<Vec<_>>();
+        verify_spend(&tx, &prevouts).unwrap();
     }
 }
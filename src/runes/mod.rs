@@ -0,0 +1,4 @@
+pub mod rune;
+pub mod rune_id;
+pub mod runestone;
+pub mod varint;
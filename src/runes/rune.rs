@@ -0,0 +1,196 @@
+use core::fmt;
+use core::str::FromStr;
+
+use crate::errors::{AccountError, Result};
+
+/// Separator shown between spaced rune characters, e.g. `UNCOMMON•GOODS`.
+pub const SPACER: char = '•';
+
+/// A rune name, stored as its modified base-26 integer (`A` = 0, `Z` = 25, `AA` = 26, ...).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rune(pub u128);
+
+impl Rune {
+    /// Bytes committed to in the taproot script of an etching's reveal input:
+    /// the little-endian name with trailing zero bytes removed.
+    pub fn commitment(self) -> Vec<u8> {
+        let bytes = self.0.to_le_bytes();
+
+        let mut end = bytes.len();
+        while end > 0 && bytes[end - 1] == 0 {
+            end -= 1;
+        }
+
+        bytes[..end].to_vec()
+    }
+}
+
+impl fmt::Display for Rune {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut n = self.0;
+        if n == u128::MAX {
+            return write!(f, "BCGDENLQRQWDSLRUGSNLBTMFIJAV");
+        }
+
+        n += 1;
+        let mut symbol = String::new();
+        while n > 0 {
+            symbol.push(char::from(b'A' + ((n - 1) % 26) as u8));
+            n = (n - 1) / 26;
+        }
+
+        for c in symbol.chars().rev() {
+            write!(f, "{c}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Rune {
+    type Err = AccountError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Err(AccountError::InvalidRune(s.to_string()));
+        }
+
+        let overflow = || AccountError::InvalidRune(format!("{s} is out of range"));
+
+        let mut x = 0u128;
+        for (i, c) in s.chars().enumerate() {
+            if i > 0 {
+                x = x.checked_add(1).ok_or_else(overflow)?;
+            }
+            x = x.checked_mul(26).ok_or_else(overflow)?;
+            match c {
+                'A'..='Z' => {
+                    x = x
+                        .checked_add(u128::from(c) - u128::from('A'))
+                        .ok_or_else(overflow)?;
+                }
+                _ => return Err(AccountError::InvalidRune(format!("invalid character `{c}`"))),
+            }
+        }
+
+        Ok(Rune(x))
+    }
+}
+
+/// A rune name together with the bitfield of spacers displayed between its characters.
+/// Bit `i` of `spacers` places a spacer after character `i`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SpacedRune {
+    pub rune: Rune,
+    pub spacers: u32,
+}
+
+impl SpacedRune {
+    pub fn new(rune: Rune, spacers: u32) -> Self {
+        SpacedRune { rune, spacers }
+    }
+}
+
+impl fmt::Display for SpacedRune {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rune = self.rune.to_string();
+
+        for (i, c) in rune.chars().enumerate() {
+            write!(f, "{c}")?;
+
+            if i < rune.len() - 1 && self.spacers & (1 << i) != 0 {
+                write!(f, "{SPACER}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for SpacedRune {
+    type Err = AccountError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rune = String::new();
+        let mut spacers = 0u32;
+
+        for c in s.chars() {
+            match c {
+                'A'..='Z' => rune.push(c),
+                '.' | SPACER => {
+                    if rune.is_empty() {
+                        return Err(AccountError::InvalidRune("leading spacer".to_string()));
+                    }
+
+                    if rune.len() > 32 {
+                        return Err(AccountError::InvalidRune(format!("{s} is out of range")));
+                    }
+
+                    let flag = 1 << (rune.len() - 1);
+                    if spacers & flag != 0 {
+                        return Err(AccountError::InvalidRune("double spacer".to_string()));
+                    }
+
+                    spacers |= flag;
+                }
+                _ => return Err(AccountError::InvalidRune(format!("invalid character `{c}`"))),
+            }
+        }
+
+        if spacers != 0 && 32 - spacers.leading_zeros() >= rune.len().try_into().unwrap_or(u32::MAX) {
+            return Err(AccountError::InvalidRune("trailing spacer".to_string()));
+        }
+
+        Ok(SpacedRune {
+            rune: rune.parse()?,
+            spacers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rune_names() {
+        for (n, name) in [
+            (0, "A"),
+            (1, "B"),
+            (25, "Z"),
+            (26, "AA"),
+            (27, "AB"),
+            (51, "AZ"),
+            (52, "BA"),
+            (u128::MAX - 1, "BCGDENLQRQWDSLRUGSNLBTMFIJAU"),
+            (u128::MAX, "BCGDENLQRQWDSLRUGSNLBTMFIJAV"),
+        ] {
+            assert_eq!(Rune(n).to_string(), name);
+            assert_eq!(name.parse::<Rune>().unwrap(), Rune(n));
+        }
+
+        assert!("".parse::<Rune>().is_err());
+        assert!("a".parse::<Rune>().is_err());
+        assert!("BCGDENLQRQWDSLRUGSNLBTMFIJAW".parse::<Rune>().is_err());
+    }
+
+    #[test]
+    fn test_spaced_rune() {
+        let spaced: SpacedRune = "UNCOMMON•GOODS".parse().unwrap();
+        assert_eq!(spaced.rune, "UNCOMMONGOODS".parse().unwrap());
+        assert_eq!(spaced.spacers, 0b1000_0000);
+        assert_eq!(spaced.to_string(), "UNCOMMON•GOODS");
+        assert_eq!("UNCOMMON.GOODS".parse::<SpacedRune>().unwrap(), spaced);
+
+        assert!("•A".parse::<SpacedRune>().is_err());
+        assert!("A••B".parse::<SpacedRune>().is_err());
+        assert!("AB•".parse::<SpacedRune>().is_err());
+    }
+
+    #[test]
+    fn test_commitment() {
+        assert_eq!(Rune(0).commitment(), Vec::<u8>::new());
+        assert_eq!(Rune(1).commitment(), vec![1]);
+        assert_eq!(Rune(256).commitment(), vec![0, 1]);
+    }
+}
@@ -0,0 +1,123 @@
+use core::fmt;
+use core::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::errors::{AccountError, Result};
+
+/// Identifies a rune by the block height and transaction index of its etching, e.g. `840000:1`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RuneId {
+    pub block: u64,
+    pub tx: u32,
+}
+
+impl RuneId {
+    /// Returns `None` for the invalid combination of block 0 and a non-zero tx index.
+    pub fn new(block: u64, tx: u32) -> Option<RuneId> {
+        let id = RuneId { block, tx };
+
+        if id.block == 0 && id.tx > 0 {
+            return None;
+        }
+
+        Some(id)
+    }
+
+    /// Delta from `self` to `next`, as encoded in runestone edicts.
+    pub fn delta(self, next: RuneId) -> Option<(u128, u128)> {
+        let block = next.block.checked_sub(self.block)?;
+
+        let tx = if block == 0 {
+            next.tx.checked_sub(self.tx)?
+        } else {
+            next.tx
+        };
+
+        Some((block.into(), tx.into()))
+    }
+
+    /// Applies an edict delta to `self`, the inverse of [`RuneId::delta`].
+    pub fn next(self, block: u128, tx: u128) -> Option<RuneId> {
+        RuneId::new(
+            self.block.checked_add(block.try_into().ok()?)?,
+            if block == 0 {
+                self.tx.checked_add(tx.try_into().ok()?)?
+            } else {
+                tx.try_into().ok()?
+            },
+        )
+    }
+}
+
+impl fmt::Display for RuneId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.block, self.tx)
+    }
+}
+
+impl FromStr for RuneId {
+    type Err = AccountError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (block, tx) = s
+            .split_once(':')
+            .ok_or_else(|| AccountError::InvalidRuneId(s.to_string()))?;
+
+        let block = block
+            .parse()
+            .map_err(|_| AccountError::InvalidRuneId(s.to_string()))?;
+        let tx = tx
+            .parse()
+            .map_err(|_| AccountError::InvalidRuneId(s.to_string()))?;
+
+        RuneId::new(block, tx).ok_or_else(|| AccountError::InvalidRuneId(s.to_string()))
+    }
+}
+
+impl Serialize for RuneId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RuneId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rune_id_display_and_parse() {
+        let id = RuneId::new(840000, 1).unwrap();
+        assert_eq!(id.to_string(), "840000:1");
+        assert_eq!("840000:1".parse::<RuneId>().unwrap(), id);
+
+        assert!("840000".parse::<RuneId>().is_err());
+        assert!("0:1".parse::<RuneId>().is_err());
+        assert!("a:1".parse::<RuneId>().is_err());
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let start = RuneId::new(840000, 5).unwrap();
+
+        for next in [
+            RuneId::new(840000, 5).unwrap(),
+            RuneId::new(840000, 9).unwrap(),
+            RuneId::new(840123, 2).unwrap(),
+        ] {
+            let (block, tx) = start.delta(next).unwrap();
+            assert_eq!(start.next(block, tx).unwrap(), next);
+        }
+
+        assert!(start.delta(RuneId::new(840000, 1).unwrap()).is_none());
+        assert!(start.delta(RuneId::new(1, 0).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,702 @@
+use std::collections::{HashMap, VecDeque};
+
+use bitcoin::blockdata::constants::MAX_SCRIPT_ELEMENT_SIZE;
+use bitcoin::opcodes;
+use bitcoin::script::{self, Instruction, PushBytes};
+use bitcoin::{ScriptBuf, Transaction};
+
+use super::rune::Rune;
+use super::rune_id::RuneId;
+use super::varint;
+
+/// Runestones are OP_RETURN outputs whose second opcode is OP_13.
+pub const MAGIC_NUMBER: opcodes::Opcode = opcodes::all::OP_PUSHNUM_13;
+
+/// Maximum number of decimal places of a rune.
+pub const MAX_DIVISIBILITY: u8 = 38;
+
+/// Maximum value of the spacers bitfield, one bit per gap of a 28 character name.
+pub const MAX_SPACERS: u32 = 0b00000111_11111111_11111111_11111111;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag {
+    Body = 0,
+    Flags = 2,
+    Rune = 4,
+    Premine = 6,
+    Cap = 8,
+    Amount = 10,
+    HeightStart = 12,
+    HeightEnd = 14,
+    OffsetStart = 16,
+    OffsetEnd = 18,
+    Mint = 20,
+    Pointer = 22,
+    Divisibility = 1,
+    Spacers = 3,
+    Symbol = 5,
+}
+
+impl Tag {
+    fn take<const N: usize, T>(
+        self,
+        fields: &mut HashMap<u128, VecDeque<u128>>,
+        with: impl Fn([u128; N]) -> Option<T>,
+    ) -> Option<T> {
+        let field = fields.get_mut(&(self as u128))?;
+
+        let mut values = [0u128; N];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = *field.get(i)?;
+        }
+
+        let value = with(values)?;
+
+        field.drain(0..N);
+        if field.is_empty() {
+            fields.remove(&(self as u128));
+        }
+
+        Some(value)
+    }
+
+    fn encode<const N: usize>(self, values: [u128; N], payload: &mut Vec<u8>) {
+        for value in values {
+            varint::encode_to_vec(self as u128, payload);
+            varint::encode_to_vec(value, payload);
+        }
+    }
+
+    fn encode_option<T: Into<u128>>(self, value: Option<T>, payload: &mut Vec<u8>) {
+        if let Some(value) = value {
+            self.encode([value.into()], payload);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Flag {
+    Etching = 0,
+    Terms = 1,
+    Turbo = 2,
+}
+
+impl Flag {
+    fn mask(self) -> u128 {
+        1 << self as u128
+    }
+
+    fn take(self, flags: &mut u128) -> bool {
+        let mask = self.mask();
+        let set = *flags & mask != 0;
+        *flags &= !mask;
+        set
+    }
+
+    fn set(self, flags: &mut u128) {
+        *flags |= self.mask();
+    }
+}
+
+/// Reason a runestone was deciphered as a cenotaph. Cenotaphs burn all runes input to the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flaw {
+    EdictOutput,
+    EdictRuneId,
+    InvalidScript,
+    Opcode,
+    SupplyOverflow,
+    TrailingIntegers,
+    TruncatedField,
+    UnrecognizedEvenTag,
+    UnrecognizedFlag,
+    Varint,
+}
+
+/// Transfers `amount` of rune `id` to output `output`.
+/// An output equal to the number of outputs splits the amount across all non-OP_RETURN outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edict {
+    pub id: RuneId,
+    pub amount: u128,
+    pub output: u32,
+}
+
+/// Open mint terms of an etching. Heights are absolute, offsets are relative to the etching block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Terms {
+    pub amount: Option<u128>,
+    pub cap: Option<u128>,
+    pub height: (Option<u64>, Option<u64>),
+    pub offset: (Option<u64>, Option<u64>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Etching {
+    pub divisibility: Option<u8>,
+    pub premine: Option<u128>,
+    pub rune: Option<Rune>,
+    pub spacers: Option<u32>,
+    pub symbol: Option<char>,
+    pub terms: Option<Terms>,
+    pub turbo: bool,
+}
+
+impl Etching {
+    /// Total supply of the rune, or `None` if premine plus all mints overflows a u128.
+    pub fn supply(&self) -> Option<u128> {
+        let premine = self.premine.unwrap_or_default();
+        let cap = self.terms.and_then(|terms| terms.cap).unwrap_or_default();
+        let amount = self.terms.and_then(|terms| terms.amount).unwrap_or_default();
+        premine.checked_add(cap.checked_mul(amount)?)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Runestone {
+    pub edicts: Vec<Edict>,
+    pub etching: Option<Etching>,
+    pub mint: Option<RuneId>,
+    pub pointer: Option<u32>,
+}
+
+/// A malformed runestone. The etched rune name and mint are kept because they still take effect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cenotaph {
+    pub etching: Option<Rune>,
+    pub flaw: Option<Flaw>,
+    pub mint: Option<RuneId>,
+}
+
+/// Result of deciphering a transaction that carries a runestone output.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Artifact {
+    Cenotaph(Cenotaph),
+    Runestone(Runestone),
+}
+
+impl Artifact {
+    pub fn mint(&self) -> Option<RuneId> {
+        match self {
+            Artifact::Cenotaph(cenotaph) => cenotaph.mint,
+            Artifact::Runestone(runestone) => runestone.mint,
+        }
+    }
+}
+
+enum Payload {
+    Valid(Vec<u8>),
+    Invalid(Flaw),
+}
+
+struct Message {
+    flaw: Option<Flaw>,
+    edicts: Vec<Edict>,
+    fields: HashMap<u128, VecDeque<u128>>,
+}
+
+impl Message {
+    fn from_integers(tx: &Transaction, payload: &[u128]) -> Self {
+        let mut edicts = Vec::new();
+        let mut fields = HashMap::<u128, VecDeque<u128>>::new();
+        let mut flaw = None;
+
+        let mut i = 0;
+        while i < payload.len() {
+            let tag = payload[i];
+
+            if tag == Tag::Body as u128 {
+                let mut id = RuneId::default();
+                for chunk in payload[i + 1..].chunks(4) {
+                    if chunk.len() != 4 {
+                        flaw.get_or_insert(Flaw::TrailingIntegers);
+                        break;
+                    }
+
+                    let Some(next) = id.next(chunk[0], chunk[1]) else {
+                        flaw.get_or_insert(Flaw::EdictRuneId);
+                        break;
+                    };
+
+                    let Some(output) = u32::try_from(chunk[3])
+                        .ok()
+                        .filter(|output| *output as usize <= tx.output.len())
+                    else {
+                        flaw.get_or_insert(Flaw::EdictOutput);
+                        break;
+                    };
+
+                    id = next;
+                    edicts.push(Edict {
+                        id,
+                        amount: chunk[2],
+                        output,
+                    });
+                }
+                break;
+            }
+
+            let Some(&value) = payload.get(i + 1) else {
+                flaw.get_or_insert(Flaw::TruncatedField);
+                break;
+            };
+
+            fields.entry(tag).or_default().push_back(value);
+
+            i += 2;
+        }
+
+        Message {
+            flaw,
+            edicts,
+            fields,
+        }
+    }
+}
+
+impl Runestone {
+    /// Builds the OP_RETURN script carrying this runestone.
+    pub fn encipher(&self) -> ScriptBuf {
+        let mut payload = Vec::new();
+
+        if let Some(etching) = self.etching {
+            let mut flags = 0;
+            Flag::Etching.set(&mut flags);
+
+            if etching.terms.is_some() {
+                Flag::Terms.set(&mut flags);
+            }
+
+            if etching.turbo {
+                Flag::Turbo.set(&mut flags);
+            }
+
+            Tag::Flags.encode([flags], &mut payload);
+
+            Tag::Rune.encode_option(etching.rune.map(|rune| rune.0), &mut payload);
+            Tag::Divisibility.encode_option(etching.divisibility, &mut payload);
+            Tag::Spacers.encode_option(etching.spacers, &mut payload);
+            Tag::Symbol.encode_option(etching.symbol, &mut payload);
+            Tag::Premine.encode_option(etching.premine, &mut payload);
+
+            if let Some(terms) = etching.terms {
+                Tag::Amount.encode_option(terms.amount, &mut payload);
+                Tag::Cap.encode_option(terms.cap, &mut payload);
+                Tag::HeightStart.encode_option(terms.height.0, &mut payload);
+                Tag::HeightEnd.encode_option(terms.height.1, &mut payload);
+                Tag::OffsetStart.encode_option(terms.offset.0, &mut payload);
+                Tag::OffsetEnd.encode_option(terms.offset.1, &mut payload);
+            }
+        }
+
+        if let Some(RuneId { block, tx }) = self.mint {
+            Tag::Mint.encode([block.into(), tx.into()], &mut payload);
+        }
+
+        Tag::Pointer.encode_option(self.pointer, &mut payload);
+
+        if !self.edicts.is_empty() {
+            varint::encode_to_vec(Tag::Body as u128, &mut payload);
+
+            let mut edicts = self.edicts.clone();
+            edicts.sort_by_key(|edict| edict.id);
+
+            let mut previous = RuneId::default();
+            for edict in edicts {
+                // edicts are sorted by id, so the delta never underflows
+                let (block, tx) = previous.delta(edict.id).unwrap();
+                varint::encode_to_vec(block, &mut payload);
+                varint::encode_to_vec(tx, &mut payload);
+                varint::encode_to_vec(edict.amount, &mut payload);
+                varint::encode_to_vec(edict.output.into(), &mut payload);
+                previous = edict.id;
+            }
+        }
+
+        let mut builder = script::Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_opcode(MAGIC_NUMBER);
+
+        for chunk in payload.chunks(MAX_SCRIPT_ELEMENT_SIZE) {
+            // chunks are at most 520 bytes, which always fits in a push
+            let push: &PushBytes = chunk.try_into().unwrap();
+            builder = builder.push_slice(push);
+        }
+
+        builder.into_script()
+    }
+
+    /// Deciphers the first runestone output of `transaction`.
+    /// Returns `None` if the transaction has no runestone output.
+    pub fn decipher(transaction: &Transaction) -> Option<Artifact> {
+        let payload = match Runestone::payload(transaction)? {
+            Payload::Valid(payload) => payload,
+            Payload::Invalid(flaw) => {
+                return Some(Artifact::Cenotaph(Cenotaph {
+                    flaw: Some(flaw),
+                    ..Default::default()
+                }));
+            }
+        };
+
+        let Some(integers) = Runestone::integers(&payload) else {
+            return Some(Artifact::Cenotaph(Cenotaph {
+                flaw: Some(Flaw::Varint),
+                ..Default::default()
+            }));
+        };
+
+        let Message {
+            mut flaw,
+            edicts,
+            mut fields,
+        } = Message::from_integers(transaction, &integers);
+
+        let mut flags = Tag::Flags
+            .take(&mut fields, |[flags]| Some(flags))
+            .unwrap_or_default();
+
+        let etching = Flag::Etching.take(&mut flags).then(|| Etching {
+            divisibility: Tag::Divisibility.take(&mut fields, |[divisibility]| {
+                let divisibility = u8::try_from(divisibility).ok()?;
+                (divisibility <= MAX_DIVISIBILITY).then_some(divisibility)
+            }),
+            premine: Tag::Premine.take(&mut fields, |[premine]| Some(premine)),
+            rune: Tag::Rune.take(&mut fields, |[rune]| Some(Rune(rune))),
+            spacers: Tag::Spacers.take(&mut fields, |[spacers]| {
+                let spacers = u32::try_from(spacers).ok()?;
+                (spacers <= MAX_SPACERS).then_some(spacers)
+            }),
+            symbol: Tag::Symbol.take(&mut fields, |[symbol]| {
+                char::from_u32(u32::try_from(symbol).ok()?)
+            }),
+            terms: Flag::Terms.take(&mut flags).then(|| Terms {
+                cap: Tag::Cap.take(&mut fields, |[cap]| Some(cap)),
+                height: (
+                    Tag::HeightStart.take(&mut fields, |[height]| u64::try_from(height).ok()),
+                    Tag::HeightEnd.take(&mut fields, |[height]| u64::try_from(height).ok()),
+                ),
+                amount: Tag::Amount.take(&mut fields, |[amount]| Some(amount)),
+                offset: (
+                    Tag::OffsetStart.take(&mut fields, |[offset]| u64::try_from(offset).ok()),
+                    Tag::OffsetEnd.take(&mut fields, |[offset]| u64::try_from(offset).ok()),
+                ),
+            }),
+            turbo: Flag::Turbo.take(&mut flags),
+        });
+
+        let mint = Tag::Mint.take(&mut fields, |[block, tx]| {
+            RuneId::new(block.try_into().ok()?, tx.try_into().ok()?)
+        });
+
+        let pointer = Tag::Pointer.take(&mut fields, |[pointer]| {
+            let pointer = u32::try_from(pointer).ok()?;
+            ((pointer as usize) < transaction.output.len()).then_some(pointer)
+        });
+
+        if etching.is_some_and(|etching| etching.supply().is_none()) {
+            flaw.get_or_insert(Flaw::SupplyOverflow);
+        }
+
+        if flags != 0 {
+            flaw.get_or_insert(Flaw::UnrecognizedFlag);
+        }
+
+        if fields.keys().any(|tag| tag % 2 == 0) {
+            flaw.get_or_insert(Flaw::UnrecognizedEvenTag);
+        }
+
+        if flaw.is_some() {
+            return Some(Artifact::Cenotaph(Cenotaph {
+                flaw,
+                mint,
+                etching: etching.and_then(|etching| etching.rune),
+            }));
+        }
+
+        Some(Artifact::Runestone(Runestone {
+            edicts,
+            etching,
+            mint,
+            pointer,
+        }))
+    }
+
+    fn payload(transaction: &Transaction) -> Option<Payload> {
+        for output in &transaction.output {
+            let mut instructions = output.script_pubkey.instructions();
+
+            if !matches!(instructions.next(), Some(Ok(Instruction::Op(opcodes::all::OP_RETURN)))) {
+                continue;
+            }
+
+            if !matches!(instructions.next(), Some(Ok(Instruction::Op(MAGIC_NUMBER)))) {
+                continue;
+            }
+
+            let mut payload = Vec::new();
+            for result in instructions {
+                match result {
+                    Ok(Instruction::PushBytes(push)) => payload.extend_from_slice(push.as_bytes()),
+                    Ok(Instruction::Op(_)) => return Some(Payload::Invalid(Flaw::Opcode)),
+                    Err(_) => return Some(Payload::Invalid(Flaw::InvalidScript)),
+                }
+            }
+
+            return Some(Payload::Valid(payload));
+        }
+
+        None
+    }
+
+    fn integers(payload: &[u8]) -> Option<Vec<u128>> {
+        let mut integers = Vec::new();
+        let mut i = 0;
+
+        while i < payload.len() {
+            let (integer, length) = varint::decode(&payload[i..]).ok()?;
+            integers.push(integer);
+            i += length;
+        }
+
+        Some(integers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, TxOut};
+
+    use super::*;
+
+    fn transaction(script_pubkey: ScriptBuf, outputs: usize) -> Transaction {
+        let mut output = vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey,
+        }];
+        output.extend((1..outputs).map(|_| TxOut {
+            value: Amount::from_sat(546),
+            script_pubkey: ScriptBuf::new(),
+        }));
+
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output,
+        }
+    }
+
+    fn payload_script(integers: &[u128]) -> ScriptBuf {
+        let mut payload = Vec::new();
+        for integer in integers {
+            varint::encode_to_vec(*integer, &mut payload);
+        }
+        let push: &PushBytes = payload.as_slice().try_into().unwrap();
+        script::Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_opcode(MAGIC_NUMBER)
+            .push_slice(push)
+            .into_script()
+    }
+
+    #[test]
+    fn test_encipher_transfer() {
+        let runestone = Runestone {
+            edicts: vec![
+                Edict {
+                    id: RuneId::new(840000, 3).unwrap(),
+                    amount: 1000,
+                    output: 1,
+                },
+                Edict {
+                    id: RuneId::new(840000, 1).unwrap(),
+                    amount: 5,
+                    output: 2,
+                },
+            ],
+            ..Default::default()
+        };
+
+        // body tag, then edicts sorted by id and delta encoded
+        assert_eq!(
+            runestone.encipher(),
+            payload_script(&[0, 840000, 1, 5, 2, 0, 2, 1000, 1])
+        );
+    }
+
+    #[test]
+    fn test_round_trip_etching() {
+        let runestone = Runestone {
+            edicts: vec![Edict {
+                id: RuneId::default(),
+                amount: 1_000_000,
+                output: 1,
+            }],
+            etching: Some(Etching {
+                divisibility: Some(2),
+                premine: Some(1_000_000),
+                rune: Some("UNCOMMONGOODS".parse().unwrap()),
+                spacers: Some(0b1000_0000),
+                symbol: Some('⧉'),
+                terms: Some(Terms {
+                    amount: Some(100),
+                    cap: Some(10_000),
+                    height: (Some(840000), Some(1050000)),
+                    offset: (None, Some(1000)),
+                }),
+                turbo: true,
+            }),
+            mint: None,
+            pointer: Some(1),
+        };
+
+        let tx = transaction(runestone.encipher(), 2);
+
+        assert_eq!(
+            Runestone::decipher(&tx),
+            Some(Artifact::Runestone(runestone))
+        );
+    }
+
+    #[test]
+    fn test_round_trip_mint() {
+        let runestone = Runestone {
+            mint: Some(RuneId::new(840000, 1).unwrap()),
+            ..Default::default()
+        };
+
+        let tx = transaction(runestone.encipher(), 2);
+
+        assert_eq!(
+            Runestone::decipher(&tx),
+            Some(Artifact::Runestone(runestone))
+        );
+    }
+
+    #[test]
+    fn test_large_payload_is_split_into_pushes() {
+        let runestone = Runestone {
+            edicts: (0..100)
+                .map(|i| Edict {
+                    id: RuneId::new(840000 + i, 1).unwrap(),
+                    amount: u128::MAX,
+                    output: 0,
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let script = runestone.encipher();
+        assert!(script.len() > MAX_SCRIPT_ELEMENT_SIZE);
+
+        let tx = transaction(script, 1);
+        assert_eq!(
+            Runestone::decipher(&tx),
+            Some(Artifact::Runestone(runestone))
+        );
+    }
+
+    #[test]
+    fn test_decipher_ignores_non_runestone_outputs() {
+        let tx = transaction(ScriptBuf::new_op_return([0u8; 4]), 1);
+        assert_eq!(Runestone::decipher(&tx), None);
+    }
+
+    #[test]
+    fn test_decipher_cenotaphs() {
+        let cenotaph = |flaw| {
+            Some(Artifact::Cenotaph(Cenotaph {
+                flaw: Some(flaw),
+                ..Default::default()
+            }))
+        };
+
+        let opcode = script::Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_opcode(MAGIC_NUMBER)
+            .push_opcode(opcodes::all::OP_VERIFY)
+            .into_script();
+        assert_eq!(Runestone::decipher(&transaction(opcode, 1)), cenotaph(Flaw::Opcode));
+
+        let varint = script::Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_opcode(MAGIC_NUMBER)
+            .push_slice([0x80])
+            .into_script();
+        assert_eq!(Runestone::decipher(&transaction(varint, 1)), cenotaph(Flaw::Varint));
+
+        assert_eq!(
+            Runestone::decipher(&transaction(payload_script(&[Tag::Pointer as u128]), 1)),
+            cenotaph(Flaw::TruncatedField)
+        );
+        assert_eq!(
+            Runestone::decipher(&transaction(payload_script(&[0, 1, 1, 1]), 1)),
+            cenotaph(Flaw::TrailingIntegers)
+        );
+        assert_eq!(
+            Runestone::decipher(&transaction(payload_script(&[0, 0, 1, 1, 0]), 1)),
+            cenotaph(Flaw::EdictRuneId)
+        );
+        assert_eq!(
+            Runestone::decipher(&transaction(payload_script(&[0, 1, 1, 1, 2]), 1)),
+            cenotaph(Flaw::EdictOutput)
+        );
+        assert_eq!(
+            Runestone::decipher(&transaction(payload_script(&[Tag::Flags as u128, 1 << 5]), 1)),
+            cenotaph(Flaw::UnrecognizedFlag)
+        );
+        assert_eq!(
+            Runestone::decipher(&transaction(payload_script(&[24, 1]), 1)),
+            cenotaph(Flaw::UnrecognizedEvenTag)
+        );
+    }
+
+    #[test]
+    fn test_cenotaph_keeps_etched_rune_and_mint() {
+        let tx = transaction(
+            payload_script(&[
+                Tag::Flags as u128,
+                Flag::Etching.mask() | Flag::Terms.mask(),
+                Tag::Rune as u128,
+                4,
+                Tag::Premine as u128,
+                u128::MAX,
+                Tag::Cap as u128,
+                2,
+                Tag::Amount as u128,
+                2,
+                Tag::Mint as u128,
+                1,
+                Tag::Mint as u128,
+                0,
+            ]),
+            1,
+        );
+
+        assert_eq!(
+            Runestone::decipher(&tx),
+            Some(Artifact::Cenotaph(Cenotaph {
+                etching: Some(Rune(4)),
+                flaw: Some(Flaw::SupplyOverflow),
+                mint: Some(RuneId::new(1, 0).unwrap()),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_unknown_odd_tags_are_ignored() {
+        let tx = transaction(payload_script(&[Tag::Pointer as u128, 0, 127, 5]), 1);
+
+        assert_eq!(
+            Runestone::decipher(&tx),
+            Some(Artifact::Runestone(Runestone {
+                pointer: Some(0),
+                ..Default::default()
+            }))
+        );
+    }
+}
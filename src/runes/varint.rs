@@ -0,0 +1,75 @@
+use crate::errors::{AccountError, Result};
+
+/// Appends `n` to `buffer` as an unsigned LEB128 varint.
+pub fn encode_to_vec(mut n: u128, buffer: &mut Vec<u8>) {
+    while n >> 7 > 0 {
+        buffer.push(n.to_le_bytes()[0] | 0b1000_0000);
+        n >>= 7;
+    }
+    buffer.push(n.to_le_bytes()[0]);
+}
+
+/// Encodes `n` as an unsigned LEB128 varint.
+pub fn encode(n: u128) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    encode_to_vec(n, &mut buffer);
+    buffer
+}
+
+/// Decodes one varint from the front of `buffer`, returning the value and the number of bytes read.
+/// Fails if the varint is unterminated, longer than 19 bytes or does not fit in a u128.
+pub fn decode(buffer: &[u8]) -> Result<(u128, usize)> {
+    let mut n = 0u128;
+
+    for (i, &byte) in buffer.iter().enumerate() {
+        if i > 18 {
+            return Err(AccountError::InvalidVarint("overlong".to_string()));
+        }
+
+        let value = u128::from(byte) & 0b0111_1111;
+
+        if i == 18 && value & 0b0111_1100 != 0 {
+            return Err(AccountError::InvalidVarint("overflow".to_string()));
+        }
+
+        n |= value << (7 * i);
+
+        if byte & 0b1000_0000 == 0 {
+            return Ok((n, i + 1));
+        }
+    }
+
+    Err(AccountError::InvalidVarint("unterminated".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_known_values() {
+        assert_eq!(encode(0), vec![0x00]);
+        assert_eq!(encode(127), vec![0x7f]);
+        assert_eq!(encode(128), vec![0x80, 0x01]);
+        assert_eq!(encode(300), vec![0xac, 0x02]);
+        assert_eq!(encode(u128::MAX).len(), 19);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for n in [0, 1, 127, 128, 16_383, 16_384, u64::MAX as u128, u128::MAX] {
+            let encoded = encode(n);
+            assert_eq!(decode(&encoded).unwrap(), (n, encoded.len()));
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(decode(&[0x80]).is_err());
+        assert!(decode(&[0x80; 19]).is_err());
+
+        let mut overflow = vec![0xff; 18];
+        overflow.push(0x04);
+        assert!(decode(&overflow).is_err());
+    }
+}
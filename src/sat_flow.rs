@@ -0,0 +1,175 @@
+use crate::errors::{AccountError, Result};
+use crate::psbt::UnSignedPsbt;
+use crate::utils::UnspentOutput;
+
+/// Where a sat ends up after the transaction is mined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    /// `offset` sats into output `vout`.
+    Output { vout: u32, offset: u64 },
+    /// Paid to the miner, `offset` sats into the fee.
+    Fee { offset: u64 },
+}
+
+/// Movement of one inscription from its input to its destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InscriptionFlow {
+    pub inscription_id: String,
+    /// index of the spending input
+    pub input: usize,
+    pub destination: Destination,
+}
+
+impl InscriptionFlow {
+    pub fn vout(&self) -> Option<u32> {
+        match self.destination {
+            Destination::Output { vout, .. } => Some(vout),
+            Destination::Fee { .. } => None,
+        }
+    }
+
+    /// True if the inscription lands on the first sat of its output, which is what wallets and
+    /// marketplaces expect. Otherwise the output needs a padding adjustment before it.
+    pub fn is_first_sat(&self) -> bool {
+        matches!(self.destination, Destination::Output { offset: 0, .. })
+    }
+}
+
+/// Maps every inscription spent by `psbt` to where it lands under ordinal theory's
+/// first-in-first-out rule: the n-th sat spent by the inputs becomes the n-th sat of the outputs,
+/// and sats past the last output are paid as fee.
+///
+/// `utxos` must contain the utxo of every input, with inscription offsets relative to the utxo.
+pub fn inscription_flows(psbt: &UnSignedPsbt, utxos: &[UnspentOutput]) -> Result<Vec<InscriptionFlow>> {
+    let mut output_ends = Vec::new();
+    let mut end = 0u64;
+    for output in &psbt.outputs {
+        end += output.value.to_sat();
+        output_ends.push(end);
+    }
+
+    let mut flows = Vec::new();
+    let mut input_start = 0u64;
+    for (input, txin) in psbt.inputs.iter().enumerate() {
+        let utxo = utxos
+            .iter()
+            .find(|utxo| utxo.outpoint() == txin.previous_output)
+            .ok_or_else(|| {
+                AccountError::Other(format!("missing utxo for input {}", txin.previous_output))
+            })?;
+        let value = utxo.satoshis.to_sat();
+
+        for inscription in &utxo.inscriptions {
+            if inscription.offset >= value {
+                return Err(AccountError::Other(format!(
+                    "inscription {} offset {} is outside its {} sat utxo",
+                    inscription.inscription_id, inscription.offset, value
+                )));
+            }
+
+            let sat = input_start + inscription.offset;
+            let destination = match output_ends.iter().position(|end| sat < *end) {
+                Some(vout) => Destination::Output {
+                    vout: vout as u32,
+                    offset: sat - if vout == 0 { 0 } else { output_ends[vout - 1] },
+                },
+                None => Destination::Fee { offset: sat - end },
+            };
+
+            flows.push(InscriptionFlow {
+                inscription_id: inscription.inscription_id.clone(),
+                input,
+                destination,
+            });
+        }
+
+        input_start += value;
+    }
+
+    Ok(flows)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{Amount, Network};
+
+    use super::*;
+    use crate::account::account::Account;
+    use crate::account::address_type::AddressType;
+    use crate::utils::{dummy_inscription_utxo, dummy_utxos, InscriptionType, Inscriptions};
+
+    const MNEMONIC: &str = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    fn account() -> Account {
+        Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, None).unwrap()
+    }
+
+    fn spending(utxos: &[UnspentOutput]) -> UnSignedPsbt {
+        utxos
+            .iter()
+            .fold(UnSignedPsbt::new(), |psbt, utxo| psbt.add_input(&utxo.tx_id.to_string(), utxo.vout))
+    }
+
+    #[test]
+    fn test_inscription_flows() {
+        let account = account();
+        let address = account.address.to_string();
+        // padding utxo, then a utxo with two inscriptions, then a funding utxo
+        let plain = dummy_utxos(&account, &[Amount::from_sat(600), Amount::from_sat(50_000)]);
+        let mut inscribed = dummy_inscription_utxo(&account, Amount::from_sat(10_000));
+        let first = inscribed.inscriptions[0].inscription_id.clone();
+        let second = format!("{}i1", inscribed.tx_id);
+        inscribed
+            .inscriptions
+            .push(Inscriptions::new(second.clone(), 0, InscriptionType::Nft).with_offset(5_000));
+        let utxos = vec![plain[0].clone(), inscribed, plain[1].clone()];
+        let psbt = spending(&utxos)
+            .add_output(&address, 600)
+            .add_output(&address, 546)
+            .add_output(&address, 50_000);
+
+        let flows = inscription_flows(&psbt, &utxos).unwrap();
+        assert_eq!(
+            flows,
+            vec![
+                InscriptionFlow {
+                    inscription_id: first,
+                    input: 1,
+                    destination: Destination::Output { vout: 1, offset: 0 },
+                },
+                InscriptionFlow {
+                    inscription_id: second,
+                    input: 1,
+                    destination: Destination::Output { vout: 2, offset: 4_454 },
+                },
+            ]
+        );
+        assert!(flows[0].is_first_sat());
+        assert!(!flows[1].is_first_sat());
+    }
+
+    #[test]
+    fn test_inscription_flows_into_fee() {
+        let account = account();
+        let mut inscribed = dummy_inscription_utxo(&account, Amount::from_sat(1_000));
+        inscribed.inscriptions[0].offset = 500;
+        let mut utxos = dummy_utxos(&account, &[Amount::from_sat(10_000)]);
+        utxos.push(inscribed);
+        let psbt = spending(&utxos).add_output(&account.address.to_string(), 10_200);
+
+        let flows = inscription_flows(&psbt, &utxos).unwrap();
+        assert_eq!(flows[0].destination, Destination::Fee { offset: 300 });
+        assert_eq!(flows[0].vout(), None);
+    }
+
+    #[test]
+    fn test_inscription_offset_out_of_range() {
+        let account = account();
+        let mut inscribed = dummy_inscription_utxo(&account, Amount::from_sat(546));
+        inscribed.inscriptions[0].offset = 546;
+        let utxos = vec![inscribed];
+        let psbt = spending(&utxos).add_output(&account.address.to_string(), 546);
+
+        assert!(inscription_flows(&psbt, &utxos).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+//! The secp256k1 context shared by the whole crate.
+
+use std::sync::OnceLock;
+
+use bitcoin::secp256k1::{All, Secp256k1};
+
+/// A signing and verification context, built on first use. Building one precomputes tables
+/// of several hundred kilobytes, far more work than a key derivation, so reuse this one
+/// instead of calling [`Secp256k1::new`].
+pub fn context() -> &'static Secp256k1<All> {
+    static CONTEXT: OnceLock<Secp256k1<All>> = OnceLock::new();
+    CONTEXT.get_or_init(Secp256k1::new)
+}
@@ -0,0 +1,212 @@
+use bitcoin::{Amount, FeeRate, Transaction};
+
+use crate::account::account::Account;
+use crate::account::address_type::AddressType;
+use crate::coin_selection::{
+    check_asset_inputs, input_vbytes, verify_inscription_transfers, AssetPolicy, DUST_LIMIT, OUTPUT_VBYTES,
+    TX_OVERHEAD_VBYTES,
+};
+use crate::errors::{AccountError, Result};
+use crate::psbt::{finalize_psbt, sign_psbt, UnSignedPsbt};
+use crate::utils::UnspentOutput;
+
+/// an uncompressed public key is 32 bytes longer than a compressed one
+const UNCOMPRESSED_KEY_EXTRA_VBYTES: u64 = 32;
+
+/// Builds a fully signed transaction spending every utxo in `utxos` to `destination.address`,
+/// minus the fee at `fee_rate`.
+///
+/// The utxos may pay to any address of the `wif` key: P2PKH, P2SH-P2WPKH, P2WPKH or P2TR, or
+/// only P2PKH for an uncompressed key as found on old paper wallets. The `wif` must be encoded
+/// for the network of `destination`.
+///
+/// Utxos carrying inscriptions or runes are only swept when requested by `policy`. They are
+/// spent first so their sats land in the destination output and the fee comes out of the plain
+/// utxos, and the sweep fails with [`AccountError::InscriptionInFee`] if the fee would still
+/// take an inscription.
+pub fn sweep(
+    wif: &str,
+    utxos: &[UnspentOutput],
+    destination: &Account,
+    policy: &AssetPolicy,
+    fee_rate: FeeRate,
+) -> Result<Transaction> {
+    // every key has a P2PKH address, compressed keys also have the others
+    let mut accounts = vec![Account::from_wif(wif, destination.network, AddressType::P2PKH)?];
+    if accounts[0].public_key.compressed {
+        for address_type in [AddressType::P2SH_P2WPKH, AddressType::P2WPKH, AddressType::P2TR] {
+            accounts.push(Account::from_wif(wif, destination.network, address_type)?);
+        }
+    }
+    if utxos.is_empty() {
+        return Err(AccountError::InsufficientFunds { required: DUST_LIMIT.to_sat(), available: 0 });
+    }
+
+    // stable, so the caller's order is kept within both groups
+    let mut ordered: Vec<&UnspentOutput> = utxos.iter().collect();
+    ordered.sort_by_key(|utxo| !utxo.has_assets());
+
+    let mut unsigned = UnSignedPsbt::new();
+    let mut vbytes = TX_OVERHEAD_VBYTES + OUTPUT_VBYTES;
+    for utxo in ordered {
+        let account = accounts
+            .iter()
+            .find(|account| account.address.script_pubkey() == utxo.script_publkey)
+            .ok_or_else(|| AccountError::ForeignUtxo(utxo.outpoint().to_string()))?;
+        vbytes += input_vbytes(&account.address_type);
+        if !account.public_key.compressed {
+            vbytes += UNCOMPRESSED_KEY_EXTRA_VBYTES;
+        }
+        unsigned = unsigned.add_input(&utxo.tx_id.to_string(), utxo.vout);
+    }
+
+    let total: Amount = utxos.iter().map(|utxo| utxo.satoshis).sum();
+    let fee = fee_rate
+        .fee_vb(vbytes)
+        .ok_or_else(|| AccountError::Other("fee overflow".to_string()))?;
+    if total < fee + DUST_LIMIT {
+        return Err(AccountError::InsufficientFunds {
+            required: (fee + DUST_LIMIT).to_sat(),
+            available: total.to_sat(),
+        });
+    }
+    let unsigned = unsigned.add_output(&destination.address.to_string(), (total - fee).to_sat());
+    check_asset_inputs(&unsigned, utxos, policy)?;
+    let expected: Vec<(&str, u32)> = utxos
+        .iter()
+        .flat_map(|utxo| &utxo.inscriptions)
+        .map(|inscription| (inscription.inscription_id.as_str(), 0))
+        .collect();
+    verify_inscription_transfers(&unsigned, utxos, &expected)?;
+
+    let mut psbt = unsigned.to_psbt(utxos)?;
+    for account in &accounts {
+        sign_psbt(&mut psbt, account)?;
+    }
+    finalize_psbt(psbt)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Network;
+
+    use super::*;
+    use crate::runes::rune_id::RuneId;
+    use crate::utils::{dummy_inscription_utxo, dummy_rune_utxo, dummy_utxos};
+
+    const MNEMONIC: &str = "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    #[test]
+    fn test_sweep_every_address_form() {
+        let destination =
+            Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2TR, None).unwrap();
+        let wif = Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, Some([0, 7]))
+            .unwrap()
+            .expose_wif_private_key()
+            .to_string();
+
+        let mut utxos = Vec::new();
+        for address_type in [
+            AddressType::P2PKH,
+            AddressType::P2SH_P2WPKH,
+            AddressType::P2WPKH,
+            AddressType::P2TR,
+        ] {
+            let imported = Account::from_wif(&wif, Network::Bitcoin, address_type).unwrap();
+            utxos.extend(dummy_utxos(&imported, &[Amount::from_sat(10_000)]));
+        }
+
+        let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+        let tx = sweep(&wif, &utxos, &destination, &AssetPolicy::new(), fee_rate).unwrap();
+
+        assert_eq!(tx.input.len(), 4);
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].script_pubkey, destination.address.script_pubkey());
+        assert!(tx.input.iter().all(|txin| !txin.script_sig.is_empty() || !txin.witness.is_empty()));
+
+        // the estimate never undershoots the actual size
+        let fee = Amount::from_sat(40_000) - tx.output[0].value;
+        assert!(fee >= fee_rate.fee_vb(tx.vsize() as u64).unwrap());
+    }
+
+    #[test]
+    fn test_sweep_uncompressed_paper_wallet() {
+        let destination =
+            Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, None).unwrap();
+        let wif = "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR";
+        let imported = Account::from_wif(wif, Network::Bitcoin, AddressType::P2PKH).unwrap();
+        let utxos = dummy_utxos(&imported, &[Amount::from_sat(5_000), Amount::from_sat(7_000)]);
+
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let tx = sweep(wif, &utxos, &destination, &AssetPolicy::new(), fee_rate).unwrap();
+        let fee = Amount::from_sat(12_000) - tx.output[0].value;
+        assert!(fee >= fee_rate.fee_vb(tx.vsize() as u64).unwrap());
+    }
+
+    #[test]
+    fn test_sweep_rejects_foreign_and_dust() {
+        let destination =
+            Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, None).unwrap();
+        let wif = destination.expose_wif_private_key().to_string();
+        let other =
+            Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, Some([0, 1])).unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+
+        let foreign = dummy_utxos(&other, &[Amount::from_sat(10_000)]);
+        assert!(matches!(
+            sweep(&wif, &foreign, &destination, &AssetPolicy::new(), fee_rate),
+            Err(AccountError::ForeignUtxo(_))
+        ));
+
+        let dust = dummy_utxos(&destination, &[Amount::from_sat(1_000)]);
+        assert!(matches!(
+            sweep(&wif, &dust, &destination, &AssetPolicy::new(), fee_rate),
+            Err(AccountError::InsufficientFunds { .. })
+        ));
+        assert!(sweep(&wif, &[], &destination, &AssetPolicy::new(), fee_rate).is_err());
+    }
+
+    #[test]
+    fn test_sweep_keeps_assets_out_of_the_fee() {
+        let destination =
+            Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2TR, None).unwrap();
+        let source =
+            Account::from_mnemonic(Network::Bitcoin, MNEMONIC, AddressType::P2WPKH, Some([0, 3])).unwrap();
+        let wif = source.expose_wif_private_key().to_string();
+        let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+
+        // the inscribed utxo comes last, spending it in this order would pay its sat as fee
+        let inscribed = dummy_inscription_utxo(&source, Amount::from_sat(546));
+        let mut utxos = dummy_utxos(&source, &[Amount::from_sat(10_000)]);
+        utxos.push(inscribed.clone());
+
+        assert!(matches!(
+            sweep(&wif, &utxos, &destination, &AssetPolicy::new(), fee_rate),
+            Err(AccountError::AssetUtxo(_))
+        ));
+
+        let policy = AssetPolicy::new().request(inscribed.outpoint());
+        let tx = sweep(&wif, &utxos, &destination, &policy, fee_rate).unwrap();
+        assert_eq!(tx.input[0].previous_output, inscribed.outpoint());
+        assert_eq!(tx.output.len(), 1);
+
+        // an inscription at the end of the only utxo would go to the miner
+        let mut only = dummy_inscription_utxo(&source, Amount::from_sat(3_000));
+        only.inscriptions[0].offset = 2_900;
+        let policy = AssetPolicy::new().request(only.outpoint());
+        assert!(matches!(
+            sweep(&wif, &[only], &destination, &policy, fee_rate),
+            Err(AccountError::InscriptionInFee(_))
+        ));
+
+        let rune = dummy_rune_utxo(&source, Amount::from_sat(546), RuneId::new(840_000, 1).unwrap(), 100);
+        let mut utxos = dummy_utxos(&source, &[Amount::from_sat(10_000)]);
+        utxos.push(rune.clone());
+        assert!(matches!(
+            sweep(&wif, &utxos, &destination, &AssetPolicy::new(), fee_rate),
+            Err(AccountError::AssetUtxo(_))
+        ));
+        let tx = sweep(&wif, &utxos, &destination, &AssetPolicy::new().request(rune.outpoint()), fee_rate).unwrap();
+        assert_eq!(tx.input[0].previous_output, rune.outpoint());
+    }
+}
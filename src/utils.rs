@@ -1,47 +1,377 @@
-use bitcoin::PublicKey;
+//! this is a utility functions for generate dummy utxos for testing
+
+use bitcoin::absolute::LockTime;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, OutPoint, PublicKey, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use serde::{Deserialize, Serialize};
+
 use crate::account::account::Account;
 use crate::account::address_type::AddressType;
+use crate::runes::rune_id::RuneId;
+use crate::runes::runestone::{Edict, Runestone};
 
-/// this is a utility functions for generate dummy utxos for testing
-///
-///
-
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnspentOutput {
-    tx_id:String,
-    vout:u32,
-    satoshis:i32,
-    script_publkey:String,
-    public_key: PublicKey,
-    address_type: AddressType,
-    inscriptions: Vec<Inscriptions>,
-    runes: Option<Vec<Runes>>,
-    raw_tx:Option<String>
+    pub tx_id: Txid,
+    pub vout: u32,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub satoshis: Amount,
+    pub script_publkey: ScriptBuf,
+    pub public_key: PublicKey,
+    pub address_type: AddressType,
+    #[serde(default)]
+    pub inscriptions: Vec<Inscriptions>,
+    #[serde(default)]
+    pub runes: Option<Vec<Runes>>,
+    /// hex encoded funding transaction, needed to sign non-segwit inputs
+    #[serde(default)]
+    pub raw_tx: Option<String>,
+}
+
+impl UnspentOutput {
+    pub fn new(
+        tx_id: Txid,
+        vout: u32,
+        satoshis: Amount,
+        script_publkey: ScriptBuf,
+        public_key: PublicKey,
+        address_type: AddressType,
+    ) -> Self {
+        UnspentOutput {
+            tx_id,
+            vout,
+            satoshis,
+            script_publkey,
+            public_key,
+            address_type,
+            inscriptions: Vec::new(),
+            runes: None,
+            raw_tx: None,
+        }
+    }
 
+    pub fn with_inscriptions(mut self, inscriptions: Vec<Inscriptions>) -> Self {
+        self.inscriptions = inscriptions;
+        self
+    }
+
+    pub fn with_runes(mut self, runes: Vec<Runes>) -> Self {
+        self.runes = Some(runes);
+        self
+    }
+
+    pub fn with_raw_tx(mut self, raw_tx: String) -> Self {
+        self.raw_tx = Some(raw_tx);
+        self
+    }
+
+    pub fn outpoint(&self) -> OutPoint {
+        OutPoint::new(self.tx_id, self.vout)
+    }
+
+    /// True if the output carries inscriptions or runes and must not be spent as plain bitcoin.
+    pub fn has_assets(&self) -> bool {
+        !self.inscriptions.is_empty() || self.runes.as_ref().is_some_and(|runes| !runes.is_empty())
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Inscriptions {
-    inscription_id:String,
-    inscription_number:u32,
-    inscription_type: InscriptionType,
-    ticker:Option<String>,
+    pub inscription_id: String,
+    /// negative for cursed inscriptions
+    pub inscription_number: i64,
+    pub inscription_type: InscriptionType,
+    #[serde(default)]
+    pub ticker: Option<String>,
+    /// position of the inscribed sat within its utxo
+    #[serde(default)]
+    pub offset: u64,
 }
 
-enum InscriptionType {
-    NFT,
-    FT
+impl Inscriptions {
+    pub fn new(inscription_id: String, inscription_number: i64, inscription_type: InscriptionType) -> Self {
+        Inscriptions {
+            inscription_id,
+            inscription_number,
+            inscription_type,
+            ticker: None,
+            offset: 0,
+        }
+    }
+
+    pub fn with_ticker(mut self, ticker: String) -> Self {
+        self.ticker = Some(ticker);
+        self
+    }
+
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InscriptionType {
+    Nft,
+    Ft,
+}
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Runes {
-    rune_id:String,
-    amount:String,
+    pub rune_id: RuneId,
+    /// amount in the rune's smallest unit, serialized as a string since it may exceed u64
+    #[serde(with = "as_string")]
+    pub amount: u128,
 }
 
+impl Runes {
+    pub fn new(rune_id: RuneId, amount: u128) -> Self {
+        Runes { rune_id, amount }
+    }
+}
 
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Assets {
-    inscriptions: Option<Vec<Inscriptions>>,
-    runes: Option<Vec<Runes>>,
-    tx_id:Option<String>,
-    vout:Option<u32>,
+    #[serde(default)]
+    pub inscriptions: Option<Vec<Inscriptions>>,
+    #[serde(default)]
+    pub runes: Option<Vec<Runes>>,
+    #[serde(default)]
+    pub tx_id: Option<Txid>,
+    #[serde(default)]
+    pub vout: Option<u32>,
+}
+
+/// Builds a deterministic funding transaction paying each of `amounts` to `account.address`,
+/// and returns its outputs as utxos with `raw_tx` and `script_publkey` filled in.
+///
+/// The funding transaction spends a made-up outpoint derived from the account, the kind of
+/// fixture and the outputs, so the same arguments always produce the same txid.
+pub fn dummy_utxos(account: &Account, amounts: &[Amount]) -> Vec<UnspentOutput> {
+    let outputs = amounts
+        .iter()
+        .map(|amount| TxOut {
+            value: *amount,
+            script_pubkey: account.address.script_pubkey(),
+        })
+        .collect();
+    let tx = dummy_funding_tx(account, "utxo", outputs);
+
+    (0..amounts.len())
+        .map(|vout| dummy_utxo(account, &tx, vout as u32))
+        .collect()
+}
+
+/// Like [`dummy_utxos`] with a single output holding an inscription on its first sat.
+/// The funding transaction plays the reveal, so the inscription id is `<txid>i0`.
+pub fn dummy_inscription_utxo(account: &Account, amount: Amount) -> UnspentOutput {
+    let tx = dummy_funding_tx(
+        account,
+        "inscription",
+        vec![TxOut {
+            value: amount,
+            script_pubkey: account.address.script_pubkey(),
+        }],
+    );
+
+    let inscription = Inscriptions::new(format!("{}i0", tx.compute_txid()), 0, InscriptionType::Nft);
+    dummy_utxo(account, &tx, 0).with_inscriptions(vec![inscription])
+}
+
+/// Like [`dummy_utxos`] with a single output holding `rune_amount` of `rune_id`.
+/// The funding transaction carries a runestone whose edict sends the runes to that output.
+pub fn dummy_rune_utxo(account: &Account, amount: Amount, rune_id: RuneId, rune_amount: u128) -> UnspentOutput {
+    let runestone = Runestone {
+        edicts: vec![Edict {
+            id: rune_id,
+            amount: rune_amount,
+            output: 0,
+        }],
+        ..Default::default()
+    };
+    let tx = dummy_funding_tx(
+        account,
+        "rune",
+        vec![
+            TxOut {
+                value: amount,
+                script_pubkey: account.address.script_pubkey(),
+            },
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: runestone.encipher(),
+            },
+        ],
+    );
+
+    dummy_utxo(account, &tx, 0).with_runes(vec![Runes::new(rune_id, rune_amount)])
+}
+
+fn dummy_funding_tx(account: &Account, kind: &str, output: Vec<TxOut>) -> Transaction {
+    let mut preimage = kind.as_bytes().to_vec();
+    preimage.extend_from_slice(account.address.script_pubkey().as_bytes());
+    for txout in &output {
+        preimage.extend_from_slice(&txout.value.to_sat().to_le_bytes());
+        preimage.extend_from_slice(txout.script_pubkey.as_bytes());
+    }
+    let previous_output = OutPoint::new(Txid::from_raw_hash(sha256d::Hash::hash(&preimage)), 0);
+
+    Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output,
+    }
+}
+
+fn dummy_utxo(account: &Account, tx: &Transaction, vout: u32) -> UnspentOutput {
+    UnspentOutput::new(
+        tx.compute_txid(),
+        vout,
+        tx.output[vout as usize].value,
+        tx.output[vout as usize].script_pubkey.clone(),
+        account.public_key,
+        account.address_type,
+    )
+    .with_raw_tx(serialize_hex(tx))
 }
 
+mod as_string {
+    use core::fmt::Display;
+    use core::str::FromStr;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unspent_output_from_json() {
+        let json = r#"{
+            "tx_id": "7f6eea7dfcc5bb1e443e63e86c6a63d0b8ed9c367f6b9e4f1e5d6e4f4e3d2c1b",
+            "vout": 1,
+            "satoshis": 2500000000,
+            "script_publkey": "0014622035497e708d8efd8753eb4189a5cb4e137e4d",
+            "public_key": "024e093f4d7dac92860d6e54cf9e553dbedb209fbcfb4482013da48e37a933b7cb",
+            "address_type": "p2wpkh",
+            "inscriptions": [{
+                "inscription_id": "7f6eea7dfcc5bb1e443e63e86c6a63d0b8ed9c367f6b9e4f1e5d6e4f4e3d2c1bi0",
+                "inscription_number": -12,
+                "inscription_type": "nft"
+            }],
+            "runes": [{ "rune_id": "840000:1", "amount": "340282366920938463463374607431768211455" }]
+        }"#;
+
+        let utxo: UnspentOutput = serde_json::from_str(json).unwrap();
+
+        assert_eq!(utxo.satoshis, Amount::from_sat(2_500_000_000));
+        assert_eq!(utxo.address_type, AddressType::P2WPKH);
+        assert_eq!(utxo.inscriptions[0].inscription_number, -12);
+        assert_eq!(utxo.inscriptions[0].offset, 0);
+        assert_eq!(utxo.runes.as_ref().unwrap()[0].amount, u128::MAX);
+        assert!(utxo.script_publkey.is_p2wpkh());
+        assert!(utxo.has_assets());
+
+        let round_trip: UnspentOutput =
+            serde_json::from_str(&serde_json::to_string(&utxo).unwrap()).unwrap();
+        assert_eq!(round_trip, utxo);
+    }
+
+    #[test]
+    fn test_unspent_output_constructor() {
+        let utxo = UnspentOutput::new(
+            "7f6eea7dfcc5bb1e443e63e86c6a63d0b8ed9c367f6b9e4f1e5d6e4f4e3d2c1b".parse().unwrap(),
+            0,
+            Amount::from_sat(546),
+            ScriptBuf::new(),
+            "024e093f4d7dac92860d6e54cf9e553dbedb209fbcfb4482013da48e37a933b7cb".parse().unwrap(),
+            AddressType::P2TR,
+        );
+        assert!(!utxo.has_assets());
+
+        let utxo = utxo.with_runes(vec![Runes::new(RuneId::new(840000, 1).unwrap(), 1)]);
+        assert!(utxo.has_assets());
+    }
+
+    const TEST_MNEMONIC: &str =
+        "wish film peasant much sure thought speed print napkin hard crumble envelope";
+
+    #[test]
+    fn test_dummy_utxos_for_every_address_type() {
+        use bitcoin::consensus::encode::deserialize_hex;
+        use bitcoin::Network;
+
+        for address_type in [
+            AddressType::P2PKH,
+            AddressType::P2SH_P2WPKH,
+            AddressType::P2WPKH,
+            AddressType::P2TR,
+        ] {
+            let account = Account::from_mnemonic(Network::Regtest, TEST_MNEMONIC, address_type, None).unwrap();
+            let utxos = dummy_utxos(&account, &[Amount::from_sat(10_000), Amount::from_sat(20_000)]);
+
+            assert_eq!(utxos.len(), 2);
+            for (vout, utxo) in utxos.iter().enumerate() {
+                let tx: Transaction = deserialize_hex(utxo.raw_tx.as_ref().unwrap()).unwrap();
+                assert_eq!(tx.compute_txid(), utxo.tx_id);
+                assert_eq!(utxo.vout, vout as u32);
+                assert_eq!(tx.output[vout].value, utxo.satoshis);
+                assert_eq!(utxo.script_publkey, account.address.script_pubkey());
+                assert_eq!(utxo.address_type, address_type);
+                assert_eq!(utxo.public_key, account.public_key);
+            }
+
+            // deterministic
+            assert_eq!(utxos, dummy_utxos(&account, &[Amount::from_sat(10_000), Amount::from_sat(20_000)]));
+        }
+    }
+
+    #[test]
+    fn test_dummy_asset_utxos() {
+        use bitcoin::consensus::encode::deserialize_hex;
+        use bitcoin::Network;
+
+        use crate::runes::runestone::Artifact;
+
+        let account = Account::from_mnemonic(Network::Regtest, TEST_MNEMONIC, AddressType::P2TR, None).unwrap();
+
+        let inscribed = dummy_inscription_utxo(&account, Amount::from_sat(546));
+        assert_eq!(inscribed.inscriptions[0].inscription_id, format!("{}i0", inscribed.tx_id));
+
+        let rune_id = RuneId::new(840000, 1).unwrap();
+        let runes = dummy_rune_utxo(&account, Amount::from_sat(546), rune_id, 1_000);
+        let tx: Transaction = deserialize_hex(runes.raw_tx.as_ref().unwrap()).unwrap();
+        let Some(Artifact::Runestone(runestone)) = Runestone::decipher(&tx) else {
+            panic!("expected runestone");
+        };
+        assert_eq!(runestone.edicts[0].id, rune_id);
+        assert_eq!(runestone.edicts[0].output, runes.vout);
+        assert_ne!(inscribed.tx_id, runes.tx_id);
+        assert_ne!(inscribed.tx_id, dummy_utxos(&account, &[Amount::from_sat(546)])[0].tx_id);
+    }
+}